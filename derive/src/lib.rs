@@ -0,0 +1,243 @@
+use proc_macro::{Delimiter, Spacing, TokenStream, TokenTree};
+
+/// Defines a getter for each field of a struct with named fields, obtaining
+/// `Pin<&mut F>` for the fields marked with `#[pin]` (see `field_pin!`) and
+/// `&mut F` for the other ones (see `field_unpin!`), where `F` — field type.
+/// `#[pin(check)]` additionally rejects `Unpin` field types, which must not
+/// depend on the generic parameters then. The check is shared with
+/// `pin_assert_immovable!`, so it requires the `pin-macros` crate, which
+/// re-exports the derive.
+/// ```
+/// # use std::{marker::PhantomPinned, pin::pin};
+/// # use pin_macros::PinProject;
+/// #[derive(PinProject)]
+/// struct Foo<T> {
+///     #[pin(check)]
+///     inner: PhantomPinned,
+///     count: T,
+/// }
+///
+/// let mut foo = pin!(Foo { inner: PhantomPinned, count: 1 });
+/// let _: std::pin::Pin<&mut PhantomPinned> = foo.as_mut().inner();
+/// *foo.as_mut().count() += 1;
+/// assert_eq!(foo.count, 2);
+/// ```
+#[proc_macro_derive(PinProject, attributes(pin))]
+pub fn derive_pin_project(input: TokenStream) -> TokenStream {
+    let output = match expand(input) {
+        Ok(output) => output,
+        Err(message) => format!("compile_error!({message:?});"),
+    };
+
+    output.parse().unwrap()
+}
+
+struct Field {
+    name: String,
+    ty: Vec<TokenTree>,
+    pin: Option<Pin>,
+}
+#[derive(PartialEq)]
+enum Pin {
+    Unchecked,
+    Checked,
+}
+
+fn expand(input: TokenStream) -> Result<String, String> {
+    let tokens: Vec<TokenTree> = input.into_iter().collect();
+    let mut i = tokens
+        .iter()
+        .position(|token| matches!(token, TokenTree::Ident(ident) if is_any(ident, &["struct", "enum", "union"])))
+        .ok_or("expected a struct")?;
+
+    if !matches!(&tokens[i], TokenTree::Ident(ident) if ident.to_string() == "struct") {
+        return Err("`PinProject` may only be derived for structs".into());
+    }
+    let name = match tokens.get(i + 1) {
+        Some(TokenTree::Ident(ident)) => ident.to_string(),
+        _ => return Err("expected a struct name".into()),
+    };
+    i += 2;
+
+    let mut params = Vec::new();
+    if is_punct(tokens.get(i), '<') {
+        let start = i + 1;
+        let mut depth = 1;
+        while depth > 0 {
+            i += 1;
+            match tokens.get(i) {
+                Some(TokenTree::Punct(punct)) if punct.as_char() == '<' => depth += 1,
+                Some(TokenTree::Punct(punct)) if punct.as_char() == '>' && !is_punct(tokens.get(i - 1), '-') => depth -= 1,
+                Some(_) => {}
+                None => return Err("unclosed generic parameters".into()),
+            }
+        }
+        params = split_commas(&tokens[start..i]);
+        i += 1;
+    }
+
+    let (body, where_clause) = match tokens.last() {
+        Some(TokenTree::Group(group)) if group.delimiter() == Delimiter::Brace => {
+            (group.stream(), stringify(&tokens[i..tokens.len() - 1]))
+        }
+        _ => return Err("`PinProject` may only be derived for structs with named fields".into()),
+    };
+    let (impl_generics, type_args, type_params) = generics(&params)?;
+    let fields = split_commas(&body.into_iter().collect::<Vec<_>>())
+        .iter()
+        .map(|field| parse_field(field))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let mut output = format!("impl<{impl_generics}> {name}<{type_args}> {where_clause} {{");
+    for field in &fields {
+        let name = &field.name;
+        let ty = stringify(&field.ty);
+        // Otherwise the getter of a generic field would not compile unless
+        // the parameter is bound by `Unpin` on the struct.
+        let unpin_bound = if mentions(&field.ty, &type_params) {
+            format!("where {ty}: ::core::marker::Unpin")
+        } else {
+            String::new()
+        };
+        output += &match field.pin {
+            Some(_) => format!(
+                "fn {name}(self: ::core::pin::Pin<&mut Self>) -> ::core::pin::Pin<&mut {ty}> {{
+                    unsafe {{ self.map_unchecked_mut(|this| &mut this.{name}) }}
+                }}"
+            ),
+            None => format!(
+                "fn {name}(self: ::core::pin::Pin<&mut Self>) -> &mut {ty} {unpin_bound} {{
+                    unsafe {{ self.map_unchecked_mut(|this| &mut this.{name}).get_mut() }}
+                }}"
+            ),
+        };
+    }
+    output += "}";
+
+    let checked = fields
+        .iter()
+        .filter(|field| field.pin == Some(Pin::Checked))
+        .map(|field| format!("let _ = <{} as ::pin_macros::__MustNotBeUnpin<_>>::check;", stringify(&field.ty)))
+        .collect::<String>();
+    if !checked.is_empty() {
+        output += &format!(
+            "const _: () = {{
+                #[allow(dead_code)]
+                fn check<{impl_generics}>() {where_clause} {{
+                    {checked}
+                }}
+            }};"
+        );
+    }
+
+    Ok(output)
+}
+/// Splits generic parameters into the `impl` parameters (without defaults),
+/// the arguments of the type and the names of the type parameters.
+fn generics(params: &[Vec<TokenTree>]) -> Result<(String, String, Vec<String>), String> {
+    let mut impl_generics = Vec::new();
+    let mut type_args = Vec::new();
+    let mut type_params = Vec::new();
+
+    for param in params {
+        let end = param.iter().position(|token| is_punct(Some(token), '=')).unwrap_or(param.len());
+        impl_generics.push(stringify(&param[..end]));
+        type_args.push(match (param.first(), param.get(1)) {
+            (Some(TokenTree::Punct(punct)), Some(TokenTree::Ident(ident))) if punct.as_char() == '\'' => {
+                format!("'{ident}")
+            }
+            (Some(TokenTree::Ident(keyword)), Some(TokenTree::Ident(ident))) if keyword.to_string() == "const" => {
+                ident.to_string()
+            }
+            (Some(TokenTree::Ident(ident)), _) => {
+                type_params.push(ident.to_string());
+                ident.to_string()
+            }
+            _ => return Err("unexpected generic parameter".into()),
+        });
+    }
+
+    Ok((impl_generics.join(", "), type_args.join(", "), type_params))
+}
+fn parse_field(tokens: &[TokenTree]) -> Result<Field, String> {
+    let mut pin = None;
+    let mut i = 0;
+
+    while is_punct(tokens.get(i), '#') {
+        let Some(TokenTree::Group(attr)) = tokens.get(i + 1) else {
+            return Err("expected an attribute".into());
+        };
+        let attr: Vec<TokenTree> = attr.stream().into_iter().collect();
+        if matches!(attr.first(), Some(TokenTree::Ident(ident)) if ident.to_string() == "pin") {
+            pin = Some(match attr.get(1) {
+                None => Pin::Unchecked,
+                Some(TokenTree::Group(args)) if args.stream().to_string() == "check" => Pin::Checked,
+                Some(_) => return Err("expected `#[pin]` or `#[pin(check)]`".into()),
+            });
+        }
+        i += 2;
+    }
+    if matches!(tokens.get(i), Some(TokenTree::Ident(ident)) if ident.to_string() == "pub") {
+        i += 1;
+        if matches!(tokens.get(i), Some(TokenTree::Group(group)) if group.delimiter() == Delimiter::Parenthesis) {
+            i += 1;
+        }
+    }
+    let name = match tokens.get(i) {
+        Some(TokenTree::Ident(ident)) => ident.to_string(),
+        _ => return Err("expected a field name".into()),
+    };
+    if !is_punct(tokens.get(i + 1), ':') {
+        return Err("expected a field type".into());
+    }
+
+    Ok(Field {
+        name,
+        ty: tokens[i + 2..].to_vec(),
+        pin,
+    })
+}
+/// Splits tokens by commas outside of angle brackets, skipping empty parts.
+fn split_commas(tokens: &[TokenTree]) -> Vec<Vec<TokenTree>> {
+    let mut parts = vec![Vec::new()];
+    let mut depth = 0;
+
+    for (i, token) in tokens.iter().enumerate() {
+        match token {
+            TokenTree::Punct(punct) if punct.as_char() == ',' && depth == 0 => {
+                parts.push(Vec::new());
+                continue;
+            }
+            TokenTree::Punct(punct) if punct.as_char() == '<' => depth += 1,
+            TokenTree::Punct(punct) if punct.as_char() == '>' => {
+                let arrow = i > 0
+                    && matches!(&tokens[i - 1], TokenTree::Punct(prev) if prev.as_char() == '-' && prev.spacing() == Spacing::Joint);
+                if !arrow {
+                    depth -= 1;
+                }
+            }
+            _ => {}
+        }
+        parts.last_mut().unwrap().push(token.clone());
+    }
+
+    parts.retain(|part| !part.is_empty());
+    parts
+}
+/// Checks whether the tokens mention any of the identifiers.
+fn mentions(tokens: &[TokenTree], idents: &[String]) -> bool {
+    tokens.iter().any(|token| match token {
+        TokenTree::Ident(ident) => idents.contains(&ident.to_string()),
+        TokenTree::Group(group) => mentions(&group.stream().into_iter().collect::<Vec<_>>(), idents),
+        _ => false,
+    })
+}
+fn stringify(tokens: &[TokenTree]) -> String {
+    tokens.iter().cloned().collect::<TokenStream>().to_string()
+}
+fn is_punct(token: Option<&TokenTree>, ch: char) -> bool {
+    matches!(token, Some(TokenTree::Punct(punct)) if punct.as_char() == ch)
+}
+fn is_any(ident: &proc_macro::Ident, keywords: &[&str]) -> bool {
+    keywords.contains(&ident.to_string().as_str())
+}
@@ -1,103 +1,4941 @@
-use std::mem::{self, MaybeUninit};
+#![cfg_attr(not(feature = "std"), no_std)]
+
+#[cfg(feature = "alloc")]
+extern crate alloc;
+
+#[cfg(feature = "alloc")]
+use alloc::boxed::Box;
+use core::{
+    mem::{self, MaybeUninit},
+    pin::Pin,
+    ptr,
+};
+
+#[cfg(feature = "derive")]
+pub use pin_macros_derive::PinProject;
 
 /// Since `&mut MaybeUninit<T>` is writable, we are allowed to perform the
 /// following call, which is unsafe:
-/// ```
+/// ```ignore
 /// mem::take(transmute_maybe_uninit(ptr), MaybeUninit::uninit());
 /// ```
-pub unsafe fn transmute_maybe_uninit<T>(ptr: &mut T) -> &mut MaybeUninit<T> {
-    mem::transmute(ptr)
-}
+///
+/// # Safety
+///
+/// The returned reference must not be used to de-initialize `*ptr`, since
+/// the original `&mut T` keeps assuming the value to be initialized. If it
+/// was de-initialized anyway, the value must be written again before `*ptr`
+/// is used as `T`.
+///
+/// The cast itself relies on `MaybeUninit<T>` having the same size and
+/// alignment as `T`, which is guaranteed by the language, and which
+/// `pin_init_field!` depends on too.
+pub unsafe fn transmute_maybe_uninit<T>(ptr: &mut T) -> &mut MaybeUninit<T> {
+    &mut *(ptr as *mut T as *mut MaybeUninit<T>)
+}
+/// Converts the pinned storage into the pinned value, once it is fully
+/// initialized, e.g. by writing it step by step after `uninit_pin!`. Pinning
+/// is kept: the conversion only reinterprets the pointer.
+///
+/// # Safety
+///
+/// The storage must hold a valid `T`, as `MaybeUninit::assume_init_mut`
+/// requires. The storage is not dropped as `T` afterwards, so the value must
+/// still never be moved out of.
+/// ```
+/// # use std::ptr::addr_of_mut;
+/// # use pin_macros::{assume_init_pin, uninit_pin};
+/// struct Pair {
+///     a: u32,
+///     b: String,
+/// }
+///
+/// uninit_pin!(mut storage: Pair);
+/// let ptr = unsafe { storage.as_mut().get_unchecked_mut() }.as_mut_ptr();
+/// unsafe {
+///     addr_of_mut!((*ptr).a).write(1);
+///     addr_of_mut!((*ptr).b).write("b".into());
+/// }
+/// let mut pair = unsafe { assume_init_pin(storage) };
+/// assert_eq!((pair.a, pair.b.as_str()), (1, "b"));
+/// unsafe { std::ptr::drop_in_place(pair.as_mut().get_unchecked_mut()) };
+/// ```
+pub unsafe fn assume_init_pin<T>(ptr: Pin<&mut MaybeUninit<T>>) -> Pin<&mut T> {
+    Pin::new_unchecked(ptr.get_unchecked_mut().assume_init_mut())
+}
+/// Asserts that an initializer returned a pointer to the storage it was
+/// given. The initializers are safe functions, and the returned pin is all
+/// that proves the storage to be initialized, e.g. a leaked value may be
+/// returned instead.
+#[doc(hidden)]
+#[track_caller]
+pub fn __assert_init<T>(value: Pin<&mut T>, storage: *const T) {
+    if !ptr::eq(value.as_ref().get_ref(), storage) {
+        panic!("the initializer returned a pointer to another value than the storage");
+    }
+}
+/// Owns a pinned value in storage it does not own, e.g. on stack, and drops
+/// the value in place when dropped itself, so the value may be torn down
+/// before the storage goes out of scope. Made by `pin_new_guarded!`.
+pub struct PinDropGuard<'a, T> {
+    ptr: Pin<&'a mut T>,
+}
+impl<'a, T> PinDropGuard<'a, T> {
+    /// # Safety
+    ///
+    /// The value must not be dropped by anything else, nor used after the
+    /// guard is dropped.
+    pub unsafe fn new(ptr: Pin<&'a mut T>) -> Self {
+        Self { ptr }
+    }
+    pub fn as_mut(&mut self) -> Pin<&mut T> {
+        self.ptr.as_mut()
+    }
+    pub fn as_ref(&self) -> Pin<&T> {
+        self.ptr.as_ref()
+    }
+}
+impl<T> core::ops::Deref for PinDropGuard<'_, T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.ptr
+    }
+}
+impl<T> Drop for PinDropGuard<'_, T> {
+    fn drop(&mut self) {
+        unsafe { ptr::drop_in_place(self.ptr.as_mut().get_unchecked_mut()) };
+    }
+}
+/// Default number of fields an [`InitGuard`] is able to track. The macros
+/// size their guards by the fields they initialize instead.
+pub const INIT_GUARD_CAPACITY: usize = 32;
+/// Drops the fields of a partially initialized `T` in reverse order of their
+/// registration, unless disarmed. Used by `pin_init!` and `pin_try_init!` to
+/// clean up after an early return or a panic.
+///
+/// With the `debug-init` feature in debug builds, the guard also fills the
+/// storage with [`INIT_POISON`] and remembers the fields marked as
+/// initialized. Disarming it panics if some unmarked field still holds the
+/// poison in all of its bytes, which points at a forgotten field. Padding is
+/// never read, so only the fields of the `pin_struct!` types are checked (see
+/// [`__PinFields`]), and among them the ones whose type has no padding itself,
+/// e.g. integers, `bool`s and pointers. The other fields are checked once
+/// marked only, as their bytes may be uninitialized after an assignment.
+/// ```
+/// # use std::{mem::MaybeUninit, pin::pin};
+/// # use pin_macros::{pin_init, pin_struct};
+/// pin_struct! {
+///     struct Pair {
+///         a: u64,
+///         b: u64,
+///     }
+/// }
+/// impl<'a> Pair {
+///     pin_init!(fn init<'a>(this) {
+///         pin_init_write!(a = 1);
+///         // `b` is forgotten.
+///     });
+/// }
+/// let uninit = MaybeUninit::<Pair>::uninit();
+/// let uninit_ptr = pin!(uninit);
+/// # #[cfg(all(feature = "debug-init", debug_assertions))]
+/// let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+///     Pair::init(uninit_ptr);
+/// }));
+/// # #[cfg(all(feature = "debug-init", debug_assertions))]
+/// assert!(result.is_err());
+/// ```
+///
+/// The capacity `N` is the number of fields the guard tracks. The macros
+/// size it at expansion time, e.g. `pin_init!` by the tracking macros in its
+/// block, so a struct of any width is tracked entirely. A hand-made guard
+/// leaves the fields beyond its capacity untracked, i.e. leaked on a panic,
+/// and skips the `debug-init` checks then.
+/// ```
+/// # use std::{cell::Cell, panic::{self, AssertUnwindSafe}};
+/// # use pin_macros::{pin_init, pin_new};
+/// struct Noisy<'a>(&'a Cell<usize>);
+/// impl Drop for Noisy<'_> {
+///     fn drop(&mut self) {
+///         self.0.set(self.0.get() + 1);
+///     }
+/// }
+/// macro_rules! wide {
+///     ($($noisy:ident)* ; $($flag:ident)*) => {
+///         struct Wide<'a> {
+///             $($noisy: Noisy<'a>,)*
+///             $($flag: u8,)*
+///         }
+///         impl<'a> Wide<'a> {
+///             pin_init!(fn init<'a>(this, drops: &'a Cell<usize>, fail: bool) {
+///                 pin_write_fields!(this { $($flag: 1,)* });
+///                 $(pin_init_write!($noisy = Noisy(drops));)*
+///                 assert!(!fail);
+///             });
+///         }
+///     };
+/// }
+/// wide!(
+///     n0 n1 n2 n3 n4 n5 n6 n7 n8 n9 n10 n11 n12 n13 n14 n15 n16 n17 n18 n19
+///     n20 n21 n22 n23 n24 n25 n26 n27 n28 n29 n30 n31 n32 n33 n34 n35 n36 n37 n38 n39;
+///     f0 f1 f2 f3 f4 f5 f6 f7 f8 f9 f10 f11 f12 f13 f14 f15 f16 f17 f18 f19
+///     f20 f21 f22 f23 f24 f25 f26 f27 f28 f29 f30 f31 f32 f33 f34 f35 f36 f37 f38 f39
+/// );
+///
+/// let drops = Cell::new(0);
+/// pin_new!(wide: Wide = init(&drops, false));
+/// assert_eq!((wide.f39, drops.get()), (1, 0));
+/// let result = panic::catch_unwind(AssertUnwindSafe(|| {
+///     pin_new!(_wide: Wide = init(&drops, true));
+/// }));
+/// assert!(result.is_err());
+/// assert_eq!(drops.get(), 40);
+/// ```
+pub struct InitGuard<T, const N: usize = INIT_GUARD_CAPACITY> {
+    ptr: *mut T,
+    len: usize,
+    drops: [Option<unsafe fn(*mut T)>; N],
+    armed: core::ops::Range<usize>,
+    #[cfg(all(feature = "debug-init", debug_assertions))]
+    marked_len: usize,
+    #[cfg(all(feature = "debug-init", debug_assertions))]
+    marked: [(usize, usize); N],
+    #[cfg(all(feature = "debug-init", debug_assertions))]
+    fields: Option<FieldsFn<T>>,
+    #[cfg(all(feature = "debug-init", debug_assertions))]
+    poison: u8,
+}
+/// Reports the offset and the size of each field of `T` through the callback,
+/// and whether the bytes of the field may be read, i.e. its type has no
+/// padding (see `__NoUninit`).
+pub type FieldsFn<T> = fn(*mut T, &mut dyn FnMut(usize, usize, bool));
+/// The byte the `debug-init` feature fills uninitialized storage with, unless
+/// overridden by [`PinPoison`].
+#[cfg(feature = "debug-init")]
+pub const INIT_POISON: u8 = 0xAA;
+/// Overrides the byte the `debug-init` feature fills the storage of `Self`
+/// with, e.g. if a field may legitimately hold nothing but `0xAA` bytes, which
+/// would be reported as forgotten. Defined for any build, but only used by
+/// the feature.
+///
+/// The byte should be one no field is expected to consist of entirely, so
+/// that an initialized field is never mistaken for a forgotten one. A byte
+/// making an invalid value of the field types is the most reliable, e.g.
+/// `0xFF` for `bool`s or a fieldless enum with fewer than 255 variants, and
+/// `0xFF` makes `NaN` floats too. For plain integers, pick a value the field
+/// never holds in practice (`0xAA` repeated is a huge number already).
+/// ```
+/// # use pin_macros::{pin_init, pin_struct, PinPoison};
+/// pin_struct! {
+///     struct Flags {
+///         // `0xAA` is a valid mask.
+///         mask: u8,
+///         enabled: bool,
+///     }
+/// }
+/// impl PinPoison for Flags {
+///     // Not a valid `bool`, so a forgotten `enabled` is still caught.
+///     const POISON: u8 = 0xFF;
+/// }
+/// impl<'a> Flags {
+///     pin_init!(fn init<'a>(this) {
+///         this.mask = 0xAA;
+///         this.enabled = true;
+///     });
+/// }
+/// # let uninit = std::mem::MaybeUninit::uninit();
+/// # Flags::init(std::pin::pin!(uninit));
+/// ```
+pub trait PinPoison {
+    const POISON: u8;
+}
+impl<T> InitGuard<T> {
+    /// Creates a guard tracking up to [`INIT_GUARD_CAPACITY`] fields.
+    ///
+    /// # Safety
+    ///
+    /// `ptr` must stay valid until the guard is either dropped or disarmed.
+    pub unsafe fn new(ptr: *mut T) -> Self {
+        Self::with_capacity(ptr)
+    }
+}
+impl<T, const N: usize> InitGuard<T, N> {
+    /// Creates a guard tracking up to `N` fields.
+    ///
+    /// # Safety
+    ///
+    /// `ptr` must stay valid until the guard is either dropped or disarmed.
+    pub unsafe fn with_capacity(ptr: *mut T) -> Self {
+        #[cfg(all(feature = "debug-init", debug_assertions))]
+        ptr::write_bytes(ptr as *mut u8, INIT_POISON, mem::size_of::<T>());
+        Self {
+            ptr,
+            len: 0,
+            drops: [None; N],
+            armed: 0..N,
+            #[cfg(all(feature = "debug-init", debug_assertions))]
+            marked_len: 0,
+            #[cfg(all(feature = "debug-init", debug_assertions))]
+            marked: [(0, 0); N],
+            #[cfg(all(feature = "debug-init", debug_assertions))]
+            fields: None,
+            #[cfg(all(feature = "debug-init", debug_assertions))]
+            poison: INIT_POISON,
+        }
+    }
+    /// Registers a function dropping an initialized field of `T`. Beyond
+    /// the capacity `N`, the field is not tracked, i.e. it is leaked rather
+    /// than dropped on an early return or a panic.
+    ///
+    /// # Safety
+    ///
+    /// `drop` must only drop the fields, which are already initialized and
+    /// not registered yet.
+    pub unsafe fn push(&mut self, drop: unsafe fn(*mut T)) {
+        if let Some(slot) = self.drops.get_mut(self.len) {
+            *slot = Some(drop);
+        }
+        self.len += 1;
+    }
+    /// Number of the registered fields, i.e. the index the next one gets.
+    pub fn registered(&self) -> usize {
+        self.len
+    }
+    /// Drops only the fields registered at the indices within `range`, e.g.
+    /// after the other ones were moved out. All of them are dropped by
+    /// default.
+    /// ```
+    /// # use std::{cell::RefCell, mem::MaybeUninit, ptr::{self, addr_of_mut}};
+    /// # use pin_macros::InitGuard;
+    /// struct Noisy<'a>(&'a RefCell<Vec<u8>>, u8);
+    /// impl Drop for Noisy<'_> {
+    ///     fn drop(&mut self) {
+    ///         self.0.borrow_mut().push(self.1);
+    ///     }
+    /// }
+    /// struct Triple<'a>(Noisy<'a>, Noisy<'a>, Noisy<'a>);
+    ///
+    /// let log = RefCell::new(Vec::new());
+    /// let mut storage = MaybeUninit::<Triple>::uninit();
+    /// let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| unsafe {
+    ///     let ptr = storage.as_mut_ptr();
+    ///     let mut guard = InitGuard::new(ptr);
+    ///     addr_of_mut!((*ptr).0).write(Noisy(&log, 0));
+    ///     guard.push(|ptr| ptr::drop_in_place(addr_of_mut!((*ptr).0)));
+    ///     addr_of_mut!((*ptr).1).write(Noisy(&log, 1));
+    ///     guard.push(|ptr| ptr::drop_in_place(addr_of_mut!((*ptr).1)));
+    ///     // The 1st field is moved out, so only the 2nd one is left to drop.
+    ///     drop(addr_of_mut!((*ptr).0).read());
+    ///     guard.arm(1..guard.registered());
+    ///     panic!("out of tokens");
+    /// }));
+    /// assert!(result.is_err());
+    /// assert_eq!(*log.borrow(), [0, 1]);
+    /// ```
+    pub fn arm(&mut self, range: core::ops::Range<usize>) {
+        self.armed = range;
+    }
+    /// Marks a field of `T` as initialized for the `debug-init` checks. Does
+    /// nothing without the feature. Beyond the capacity `N`, the marks are
+    /// no longer complete, so the checks are skipped.
+    #[inline(always)]
+    pub fn mark<F>(&mut self, field: *mut F) {
+        #[cfg(all(feature = "debug-init", debug_assertions))]
+        {
+            if let Some(slot) = self.marked.get_mut(self.marked_len) {
+                *slot = (field as usize - self.ptr as usize, mem::size_of::<F>());
+            }
+            self.marked_len += 1;
+        }
+        #[cfg(not(all(feature = "debug-init", debug_assertions)))]
+        let _ = field;
+    }
+    /// Sets the fields of `T` to check one by one for the `debug-init` checks.
+    /// Does nothing without the feature.
+    #[inline(always)]
+    pub fn set_fields(&mut self, fields: Option<FieldsFn<T>>) {
+        #[cfg(all(feature = "debug-init", debug_assertions))]
+        {
+            self.fields = fields;
+        }
+        #[cfg(not(all(feature = "debug-init", debug_assertions)))]
+        let _ = fields;
+    }
+    /// Fills the storage with another poison byte for the `debug-init`
+    /// checks, if any (see [`PinPoison`]). Does nothing without the feature.
+    ///
+    /// # Safety
+    ///
+    /// No field of `T` may be initialized yet.
+    #[inline(always)]
+    pub unsafe fn set_poison(&mut self, poison: Option<u8>) {
+        #[cfg(all(feature = "debug-init", debug_assertions))]
+        if let Some(poison) = poison {
+            self.poison = poison;
+            ptr::write_bytes(self.ptr as *mut u8, poison, mem::size_of::<T>());
+        }
+        #[cfg(not(all(feature = "debug-init", debug_assertions)))]
+        let _ = poison;
+    }
+    /// Asserts for the `debug-init` checks that a field of `T` another one
+    /// depends on is initialized, i.e. it is either marked or no longer
+    /// poisoned in all of its bytes. The bytes are only read if `readable`,
+    /// i.e. the type of the field has no padding, otherwise an unmarked
+    /// field is assumed to be initialized. Does nothing without the feature.
+    #[inline(always)]
+    #[track_caller]
+    pub fn check_init<F>(&self, field: *const F, readable: bool, name: &str) {
+        #[cfg(all(feature = "debug-init", debug_assertions))]
+        {
+            if self.marked_len > N || !readable || mem::size_of::<F>() == 0 {
+                return;
+            }
+            let offset = field as usize - self.ptr as usize;
+            let marked = self.marked[..self.marked_len].iter().any(|&(start, len)| (start, len) == (offset, mem::size_of::<F>()));
+            if marked {
+                return;
+            }
+            // Without padding, every byte is either still poison or written.
+            let bytes = unsafe { core::slice::from_raw_parts(field as *const u8, mem::size_of::<F>()) };
+            if bytes.iter().all(|&byte| byte == self.poison) {
+                panic!(
+                    "`{}` is used before being initialized in `{}`",
+                    name,
+                    core::any::type_name::<T>(),
+                );
+            }
+        }
+        #[cfg(not(all(feature = "debug-init", debug_assertions)))]
+        let _ = (field, readable, name);
+    }
+    /// Forgets all the registered fields, leaving them initialized. Nothing
+    /// is left to run on drop then.
+    #[track_caller]
+    pub fn disarm(self) {
+        #[cfg(all(feature = "debug-init", debug_assertions))]
+        self.check_poison();
+        mem::forget(self);
+    }
+    #[cfg(all(feature = "debug-init", debug_assertions))]
+    #[track_caller]
+    fn check_poison(&self) {
+        // The padding of `T` and of its fields may be uninitialized, so only
+        // the known fields without padding are read.
+        let fields = match self.fields {
+            Some(fields) if self.marked_len <= N => fields,
+            _ => return,
+        };
+        let marked = |offset: usize| self.marked[..self.marked_len].iter().any(|&(start, len)| (start..start + len).contains(&offset));
+
+        // Asserted outside of the closure, which can't track the caller.
+        let mut uninit = None;
+        fields(self.ptr, &mut |offset, size, readable| {
+            if size == 0 || !readable || marked(offset) {
+                return;
+            }
+            let bytes = unsafe { core::slice::from_raw_parts((self.ptr as *const u8).add(offset), size) };
+            if bytes.iter().all(|&byte| byte == self.poison) {
+                uninit = uninit.or(Some(offset));
+            }
+        });
+        if let Some(offset) = uninit {
+            panic!(
+                "`{}` still holds poison at offset {}, the field is likely uninitialized",
+                core::any::type_name::<T>(),
+                offset,
+            );
+        }
+    }
+}
+impl<T, const N: usize> Drop for InitGuard<T, N> {
+    fn drop(&mut self) {
+        let end = self.armed.end.min(self.len);
+        let start = self.armed.start.min(end);
+        for drop in self.drops[start.min(N)..end.min(N)].iter().rev().flatten() {
+            unsafe { drop(self.ptr) };
+        }
+    }
+}
+/// Emits a `TRACE` event with the `pin_macros` target, naming the type and
+/// the field about to be initialized, with the `tracing` feature. Does nothing
+/// without the feature. Used by `pin_init!` and `pin_field_init!`.
+/// ```
+/// # #[cfg(feature = "tracing")]
+/// # fn main() {
+/// # use std::{mem::MaybeUninit, pin::pin, sync::Mutex};
+/// # use pin_macros::pin_init;
+/// # use tracing::{field::{Field, Visit}, span, Event, Metadata, Subscriber};
+/// struct Fields(Mutex<Vec<String>>);
+/// impl Subscriber for Fields {
+///     fn event(&self, event: &Event) {
+///         struct Visitor<'a>(&'a mut Vec<String>);
+///         impl Visit for Visitor<'_> {
+///             fn record_str(&mut self, field: &Field, value: &str) {
+///                 if field.name() == "field" {
+///                     self.0.push(value.to_owned());
+///                 }
+///             }
+///             fn record_debug(&mut self, _: &Field, _: &dyn std::fmt::Debug) {}
+///         }
+///         event.record(&mut Visitor(&mut self.0.lock().unwrap()));
+///     }
+///     fn enabled(&self, metadata: &Metadata) -> bool {
+///         metadata.target() == "pin_macros"
+///     }
+///     fn new_span(&self, _: &span::Attributes) -> span::Id {
+///         span::Id::from_u64(1)
+///     }
+///     fn record(&self, _: &span::Id, _: &span::Record) {}
+///     fn record_follows_from(&self, _: &span::Id, _: &span::Id) {}
+///     fn enter(&self, _: &span::Id) {}
+///     fn exit(&self, _: &span::Id) {}
+/// }
+/// struct Pair {
+///     a: u32,
+///     b: u32,
+/// }
+/// impl<'a> Pair {
+///     pin_init!(fn init<'a>(this) {
+///         pin_init_write!(b = 2);
+///         pin_init_write!(a = 1);
+///     });
+/// }
+///
+/// let fields = std::sync::Arc::new(Fields(Mutex::new(Vec::new())));
+/// tracing::subscriber::with_default(fields.clone(), || {
+///     let uninit = MaybeUninit::<Pair>::uninit();
+///     Pair::init(pin!(uninit));
+/// });
+/// assert_eq!(*fields.0.lock().unwrap(), ["b", "a"]);
+/// # }
+/// # #[cfg(not(feature = "tracing"))]
+/// # fn main() {}
+/// ```
+#[doc(hidden)]
+#[inline(always)]
+pub fn __trace_init<T: ?Sized>(ptr: *const T, field: &'static str) {
+    // Only infers the type.
+    let _ = ptr;
+    #[cfg(feature = "tracing")]
+    tracing::trace!(target: "pin_macros", r#type = core::any::type_name::<T>(), field, "initializing a field");
+    #[cfg(not(feature = "tracing"))]
+    let _ = field;
+}
+
+/// Field layout of a struct, implemented by `pin_struct!` to let the
+/// `debug-init` checks find forgotten fields precisely.
+#[doc(hidden)]
+pub trait __PinFields {
+    fn fields(ptr: *mut Self, field: &mut dyn FnMut(usize, usize, bool));
+}
+#[doc(hidden)]
+pub fn __pin_field<T, F>(ptr: *mut T, field_ptr: *mut F, readable: bool, field: &mut dyn FnMut(usize, usize, bool)) {
+    field(field_ptr as usize - ptr as usize, mem::size_of::<F>(), readable);
+}
+/// Types having no padding or other uninitialized bytes in any of their
+/// values, so the `debug-init` checks may read the bytes of such a field,
+/// whether it is still poisoned or written by a plain assignment.
+///
+/// # Safety
+///
+/// Every byte of every value of `Self` must be initialized.
+#[doc(hidden)]
+pub unsafe trait __NoUninit {}
+macro_rules! impl_no_uninit {
+    ($($t:ty),*) => {
+        $(unsafe impl __NoUninit for $t {})*
+    };
+}
+impl_no_uninit!(u8, u16, u32, u64, u128, usize, i8, i16, i32, i64, i128, isize, f32, f64, bool, char);
+unsafe impl<T: ?Sized> __NoUninit for *const T {}
+unsafe impl<T: ?Sized> __NoUninit for *mut T {}
+unsafe impl<T: ?Sized> __NoUninit for &T {}
+unsafe impl<T: ?Sized> __NoUninit for &mut T {}
+unsafe impl<T: ?Sized> __NoUninit for core::ptr::NonNull<T> {}
+// The null pointer niche covers all the bytes of `None` for thin pointers only.
+unsafe impl<T> __NoUninit for Option<&T> {}
+unsafe impl<T> __NoUninit for Option<&mut T> {}
+unsafe impl<T> __NoUninit for Option<core::ptr::NonNull<T>> {}
+unsafe impl<T: __NoUninit, const N: usize> __NoUninit for [T; N] {}
+/// Finds out whether `T` is [`__NoUninit`] by autoref specialization:
+/// `(&__BytesProbe::<T>(PhantomData)).readable()`.
+#[doc(hidden)]
+pub struct __BytesProbe<T>(pub core::marker::PhantomData<T>);
+impl<T> __BytesProbe<T> {
+    pub fn of(_ptr: *const T) -> Self {
+        Self(core::marker::PhantomData)
+    }
+}
+#[doc(hidden)]
+pub trait __ProbeBytes {
+    fn readable(&self) -> bool;
+}
+impl<T: __NoUninit> __ProbeBytes for __BytesProbe<T> {
+    fn readable(&self) -> bool {
+        true
+    }
+}
+#[doc(hidden)]
+pub trait __ProbeNoBytes {
+    fn readable(&self) -> bool {
+        false
+    }
+}
+impl<T> __ProbeNoBytes for &__BytesProbe<T> {}
+/// Obtains the [`__PinFields`] layout of `T`, if implemented, by autoref
+/// specialization: `(&__FieldsProbe::<T>(PhantomData)).fields()`.
+#[doc(hidden)]
+pub struct __FieldsProbe<T>(pub core::marker::PhantomData<T>);
+#[doc(hidden)]
+pub trait __ProbeFields<T> {
+    fn fields(&self) -> Option<FieldsFn<T>>;
+}
+impl<T: __PinFields> __ProbeFields<T> for __FieldsProbe<T> {
+    fn fields(&self) -> Option<FieldsFn<T>> {
+        Some(T::fields)
+    }
+}
+#[doc(hidden)]
+pub trait __ProbeNoFields<T> {
+    fn fields(&self) -> Option<FieldsFn<T>> {
+        None
+    }
+}
+impl<T> __ProbeNoFields<T> for &__FieldsProbe<T> {}
+/// Obtains the [`PinPoison`] byte of `T`, if implemented, by autoref
+/// specialization: `(&__PoisonProbe::<T>(PhantomData)).poison()`.
+#[doc(hidden)]
+pub struct __PoisonProbe<T>(pub core::marker::PhantomData<T>);
+#[doc(hidden)]
+pub trait __ProbePoison {
+    fn poison(&self) -> Option<u8>;
+}
+impl<T: PinPoison> __ProbePoison for __PoisonProbe<T> {
+    fn poison(&self) -> Option<u8> {
+        Some(T::POISON)
+    }
+}
+#[doc(hidden)]
+pub trait __ProbeNoPoison {
+    fn poison(&self) -> Option<u8> {
+        None
+    }
+}
+impl<T> __ProbeNoPoison for &__PoisonProbe<T> {}
+
+/// Types, which may be initialized in place by `Self::init`. `Args` is a
+/// tuple of the initializer arguments, e.g. `(u32, &'a str)` for
+/// `init(this, a: u32, b: &'a str)`, `(u32,)` for a single argument and `()`
+/// for none. Implemented by the `impl` form of `pin_init!`.
+pub trait PinInit<'a, Args>: Sized {
+    fn init(ptr: Pin<&'a mut MaybeUninit<Self>>, args: Args) -> Pin<&'a mut Self>;
+}
+/// Types having a default value, which is initialized in place, so it may be
+/// self-referential. The method may be defined using `pin_init!`.
+pub trait PinDefault<'a>: Sized {
+    fn pin_default(ptr: Pin<&'a mut MaybeUninit<Self>>) -> Pin<&'a mut Self>;
+}
+/// Drops the value of the slot, if any, and initializes its [`PinDefault`]
+/// value into the same storage. The slot is `None` until `pin_default`
+/// returns, so a panic leaves it empty. Used by `pin_reset!`.
+#[doc(hidden)]
+#[track_caller]
+pub fn __pin_reset<'a, T: PinDefault<'a>>(mut slot: Pin<&'a mut PinOption<T>>) -> Pin<&'a mut T> {
+    slot.as_mut().clear();
+    unsafe {
+        // The flag is written through the pointer, so the borrow of the value
+        // stays valid.
+        let slot: *mut PinOption<T> = slot.get_unchecked_mut();
+        let storage = &mut *ptr::addr_of_mut!((*slot).value);
+        let storage_ptr = storage.as_ptr();
+        let value = T::pin_default(Pin::new_unchecked(storage));
+        if !ptr::eq(value.as_ref().get_ref(), storage_ptr) {
+            panic!("the initializer returned a pointer to another value than the storage");
+        }
+        *ptr::addr_of_mut!((*slot).is_some) = true;
+        value
+    }
+}
+/// Allocators of storage for owned immovable values, e.g. bump arenas. Used
+/// by `pin_new_in!`.
+///
+/// # Safety
+///
+/// The returned storage must be valid for `T`, properly aligned and not used
+/// by anything else for as long as `self` is borrowed.
+pub unsafe trait PinAlloc {
+    /// Allocates uninitialized storage for `T`, or returns null if it is out
+    /// of memory.
+    fn alloc_uninit<T>(&self) -> *mut MaybeUninit<T>;
+}
+/// Ties the storage to the borrow of the allocator. Used by `pin_new_in!`.
+#[doc(hidden)]
+pub trait __PinAllocExt: PinAlloc {
+    // The storage is not used by anything else, as guaranteed by `PinAlloc`.
+    #[allow(clippy::mut_from_ref)]
+    fn __pin_uninit<T>(&self) -> Option<Pin<&mut MaybeUninit<T>>>;
+}
+impl<A: PinAlloc> __PinAllocExt for A {
+    fn __pin_uninit<T>(&self) -> Option<Pin<&mut MaybeUninit<T>>> {
+        let ptr = self.alloc_uninit::<T>();
+        (!ptr.is_null()).then(|| unsafe { Pin::new_unchecked(&mut *ptr) })
+    }
+}
+/// Types, for which the all-zero bit pattern is a valid value.
+///
+/// # Safety
+///
+/// A zeroed `Self` must be valid and safe to use.
+pub unsafe trait PinZeroable {}
+macro_rules! impl_pin_zeroable {
+    ($($type:ty),+ $(,)?) => {
+        $(unsafe impl PinZeroable for $type {})+
+    };
+}
+impl_pin_zeroable!(
+    u8, u16, u32, u64, u128, usize, i8, i16, i32, i64, i128, isize, f32, f64, bool, char, (),
+    core::marker::PhantomPinned,
+);
+unsafe impl<T: PinZeroable, const N: usize> PinZeroable for [T; N] {}
+unsafe impl<T: ?Sized> PinZeroable for core::marker::PhantomData<T> {}
+unsafe impl<T> PinZeroable for *const T {}
+unsafe impl<T> PinZeroable for *mut T {}
+unsafe impl<T: PinZeroable> PinZeroable for core::mem::MaybeUninit<T> {}
+/// Fills the pinned storage with zeros. Used by `pin_new_zeroed!`.
+pub fn init_zeroed<T: PinZeroable>(mut ptr: Pin<&mut MaybeUninit<T>>) -> Pin<&mut T> {
+    unsafe {
+        ptr::write_bytes(ptr.as_mut().get_unchecked_mut().as_mut_ptr(), 0, 1);
+        assume_init_pin(ptr)
+    }
+}
+/// Moves the value into the pinned storage. Used by `pin_init_from!`.
+pub fn init_from<T>(ptr: Pin<&mut MaybeUninit<T>>, value: T) -> Pin<&mut T> {
+    unsafe { Pin::new_unchecked(ptr.get_unchecked_mut().write(value)) }
+}
+/// Types, whose pinned values may swap their contents, see `pin_swap!`.
+///
+/// # Safety
+///
+/// After the bytes of two values are swapped and `fix_up` is called on both,
+/// each value must be valid at its new address. So `fix_up` must rebase
+/// every pointer into the value itself, and must not panic, since the swap
+/// can't be undone then. No pointer to the value may be held outside of it
+/// (e.g. by the neighbours of an intrusive list node or by a registered
+/// waker), unless it stays valid to reach the swapped contents through it.
+/// For a type without self-references, the default `fix_up` doing nothing
+/// is sufficient.
+pub unsafe trait PinSwap {
+    /// Repairs the value, which was moved here from `old`, e.g. by rebasing
+    /// its self-references from `old` onto `self`.
+    #[allow(unused_variables)]
+    fn fix_up(self: Pin<&mut Self>, old: *const Self) {}
+}
+/// Swaps the contents of two pinned values, fixing them up afterwards. Used
+/// by `pin_swap!`.
+#[doc(hidden)]
+pub fn __pin_swap<T: PinSwap>(a: Pin<&mut T>, b: Pin<&mut T>) {
+    unsafe {
+        let a_ptr: *mut T = a.get_unchecked_mut();
+        let b_ptr: *mut T = b.get_unchecked_mut();
+        // The references are unique, so the values don't overlap.
+        ptr::swap_nonoverlapping(a_ptr, b_ptr, 1);
+        Pin::new_unchecked(&mut *a_ptr).fix_up(b_ptr);
+        Pin::new_unchecked(&mut *b_ptr).fix_up(a_ptr);
+    }
+}
+/// A field not initialized yet in a [`PinBuilder`] state.
+pub struct FieldUnset;
+/// An initialized field in a [`PinBuilder`] state.
+pub struct FieldSet;
+/// The [`PinBuilder`] states of `Self`, usually tuples of [`FieldUnset`] and
+/// [`FieldSet`], one per field. Implemented by `pin_builder!`. `N` is the
+/// number of fields for the builder to track, [`INIT_GUARD_CAPACITY`] by
+/// default.
+///
+/// # Safety
+///
+/// `Start` must mark every field as not initialized, and `Done` must only be
+/// reachable from it through the [`PinBuildField`] transitions once every
+/// field is initialized.
+pub unsafe trait PinBuild<const N: usize = INIT_GUARD_CAPACITY>: Sized {
+    type Start;
+    type Done;
+}
+/// A field of `T` that [`PinBuilder`] may initialize in state `S`, moving to
+/// state `Next`. Implemented by `pin_builder!` for a marker type per field.
+///
+/// # Safety
+///
+/// The field must not be initialized in state `S` and must be initialized in
+/// state `Next`, which otherwise remains the same, so every field is
+/// initialized exactly once on the way to [`PinBuild::Done`].
+pub unsafe trait PinBuildField<T, S> {
+    type Type;
+    type Next;
+
+    /// Obtains the pointer to the field of the (possibly uninitialized) `T`.
+    ///
+    /// # Safety
+    ///
+    /// `ptr` must point to allocated storage of `T`.
+    unsafe fn project(ptr: *mut T) -> *mut Self::Type;
+}
+/// Initializes the fields of a pinned `T` one by one, tracking them in the
+/// state type `S`, so that the value is obtained only once all of them are
+/// initialized, and forgetting or repeating a field fails to compile. If the
+/// builder is dropped early, e.g. by a panicking initializer, the initialized
+/// fields are dropped in reverse order.
+///
+/// The states are described by [`PinBuild`] and [`PinBuildField`], which
+/// `pin_builder!` implements for a struct, or may be implemented by hand:
+/// ```
+/// # use std::ptr::addr_of_mut;
+/// # use pin_macros::{uninit_pin, FieldSet, FieldUnset, PinBuild, PinBuildField, PinBuilder};
+/// struct Pair {
+///     a: u32,
+///     b: String,
+/// }
+/// struct A;
+/// struct B;
+/// unsafe impl PinBuild for Pair {
+///     type Start = (FieldUnset, FieldUnset);
+///     type Done = (FieldSet, FieldSet);
+/// }
+/// unsafe impl<S> PinBuildField<Pair, (FieldUnset, S)> for A {
+///     type Type = u32;
+///     type Next = (FieldSet, S);
+///
+///     unsafe fn project(ptr: *mut Pair) -> *mut u32 {
+///         addr_of_mut!((*ptr).a)
+///     }
+/// }
+/// unsafe impl<S> PinBuildField<Pair, (S, FieldUnset)> for B {
+///     type Type = String;
+///     type Next = (S, FieldSet);
+///
+///     unsafe fn project(ptr: *mut Pair) -> *mut String {
+///         addr_of_mut!((*ptr).b)
+///     }
+/// }
+///
+/// uninit_pin!(storage: Pair);
+/// let pair = PinBuilder::new(storage).set(B, "b".into()).set(A, 1).finish();
+/// assert_eq!((pair.a, pair.b.as_str()), (1, "b"));
+/// ```
+///
+/// Building without a field does not compile:
+/// ```compile_fail
+/// # use pin_macros::{pin_builder, uninit_pin, PinBuilder};
+/// struct Pair {
+///     a: u32,
+///     b: u32,
+/// }
+/// pin_builder!(mod pair for Pair { a: u32, b: u32 });
+///
+/// uninit_pin!(storage: Pair);
+/// let pair = PinBuilder::new(storage).set(pair::a, 1).finish();
+/// ```
+pub struct PinBuilder<'a, T, S, const N: usize = INIT_GUARD_CAPACITY> {
+    ptr: *mut T,
+    guard: InitGuard<T, N>,
+    marker: core::marker::PhantomData<(&'a mut T, S)>,
+}
+impl<'a, T: PinBuild<N>, const N: usize> PinBuilder<'a, T, T::Start, N> {
+    pub fn new(ptr: Pin<&'a mut MaybeUninit<T>>) -> Self {
+        let ptr = unsafe { ptr.get_unchecked_mut() }.as_mut_ptr();
+        Self { ptr, guard: unsafe { InitGuard::with_capacity(ptr) }, marker: core::marker::PhantomData }
+    }
+}
+impl<'a, T, S, const N: usize> PinBuilder<'a, T, S, N> {
+    /// Moves the value into the field.
+    pub fn set<F: PinBuildField<T, S>>(mut self, field: F, value: F::Type) -> PinBuilder<'a, T, F::Next, N> {
+        let _ = field;
+        unsafe {
+            F::project(self.ptr).write(value);
+            self.track::<F>();
+        }
+        self.into_state()
+    }
+    /// Initializes the owned immovable field in place, e.g. by its
+    /// `pin_init!` method. The storage of the field is only lent to `init`
+    /// for the call, and `init` must return it initialized.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `init` returns a pointer to another value than the field.
+    #[track_caller]
+    pub fn init<F: PinBuildField<T, S>>(
+        mut self,
+        field: F,
+        init: impl for<'s> FnOnce(Pin<&'s mut MaybeUninit<F::Type>>) -> Pin<&'s mut F::Type>,
+    ) -> PinBuilder<'a, T, F::Next, N> {
+        let _ = field;
+        unsafe {
+            let field_ptr = F::project(self.ptr);
+            let ptr = init(Pin::new_unchecked(&mut *(field_ptr as *mut MaybeUninit<F::Type>))).as_ref().get_ref() as *const F::Type;
+            if !ptr::eq(ptr, field_ptr) {
+                panic!("the initializer returned a pointer to another value than the field");
+            }
+            self.track::<F>();
+        }
+        self.into_state()
+    }
+    /// Obtains the pointer to the value, e.g. to store it in a field as a
+    /// self-reference. It must not be dereferenced until the value is built.
+    pub fn as_mut_ptr(&self) -> *mut T {
+        self.ptr
+    }
+    unsafe fn track<F: PinBuildField<T, S>>(&mut self) {
+        self.guard.mark(F::project(self.ptr));
+        self.guard.push(|ptr| ptr::drop_in_place(F::project(ptr)));
+    }
+    fn into_state<Next>(self) -> PinBuilder<'a, T, Next, N> {
+        PinBuilder { ptr: self.ptr, guard: self.guard, marker: core::marker::PhantomData }
+    }
+}
+impl<'a, T: PinBuild<N>, const N: usize> PinBuilder<'a, T, T::Done, N> {
+    /// Obtains the value, once all fields are initialized.
+    pub fn finish(self) -> Pin<&'a mut T> {
+        self.guard.disarm();
+        unsafe { Pin::new_unchecked(&mut *self.ptr) }
+    }
+}
+/// The byte slice passed to [`init_from_bytes`] is shorter than the type.
+#[cfg(feature = "bytemuck")]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BytesLenError {
+    /// The size of the type.
+    pub expected: usize,
+    /// The length of the slice.
+    pub actual: usize,
+}
+#[cfg(feature = "bytemuck")]
+impl core::fmt::Display for BytesLenError {
+    fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+        write!(f, "expected at least {} bytes, got {}", self.expected, self.actual)
+    }
+}
+#[cfg(all(feature = "bytemuck", feature = "std"))]
+impl std::error::Error for BytesLenError {}
+/// Copies the first `size_of::<T>()` bytes into the pinned storage. Used by
+/// `pin_new_from_bytes!`.
+#[cfg(feature = "bytemuck")]
+pub fn init_from_bytes<'a, T: bytemuck::AnyBitPattern>(
+    ptr: Pin<&'a mut MaybeUninit<T>>,
+    bytes: &[u8],
+) -> Result<Pin<&'a mut T>, BytesLenError> {
+    if bytes.len() < mem::size_of::<T>() {
+        return Err(BytesLenError { expected: mem::size_of::<T>(), actual: bytes.len() });
+    }
+
+    unsafe {
+        let uninit = ptr.get_unchecked_mut();
+        // Any bit pattern is a valid `T`, and the storage is aligned anyway.
+        ptr::copy_nonoverlapping(bytes.as_ptr(), uninit.as_mut_ptr() as *mut u8, mem::size_of::<T>());
+
+        Ok(Pin::new_unchecked(uninit.assume_init_mut()))
+    }
+}
+/// A type which may be deserialized right into its pinned storage, so an
+/// immovable value does not have to be built elsewhere and moved in. Usually
+/// implemented by `pin_deserialize!`, and used by `pin_new_deserialize!`.
+#[cfg(feature = "serde")]
+pub trait PinDeserialize<'de>: Sized {
+    /// Initializes the storage from the deserializer. On an error, the
+    /// already deserialized parts are dropped, and the storage is left
+    /// uninitialized.
+    fn deserialize_pin<'a, D: serde::Deserializer<'de>>(
+        ptr: Pin<&'a mut MaybeUninit<Self>>,
+        deserializer: D,
+    ) -> Result<Pin<&'a mut Self>, D::Error>;
+}
+/// Building blocks of `pin_deserialize!`.
+#[cfg(feature = "serde")]
+#[doc(hidden)]
+pub mod __serde {
+    use super::*;
+    use core::{fmt, marker::PhantomData};
+    pub use serde::de::{Deserializer, Error, IgnoredAny, MapAccess, SeqAccess};
+    use serde::de::{DeserializeSeed, Visitor};
+
+    /// Visits the fields of a struct, writing them into the storage.
+    pub trait PinVisit<'de>: Sized {
+        const NAME: &'static str;
+        const FIELDS: &'static [&'static str];
+
+        fn visit_map<A: MapAccess<'de>>(ptr: Pin<&mut MaybeUninit<Self>>, map: A) -> Result<(), A::Error>;
+        fn visit_seq<A: SeqAccess<'de>>(ptr: Pin<&mut MaybeUninit<Self>>, seq: A) -> Result<(), A::Error>;
+    }
+    pub fn deserialize_struct<'a, 'de, T: PinVisit<'de>, D: Deserializer<'de>>(
+        mut ptr: Pin<&'a mut MaybeUninit<T>>,
+        deserializer: D,
+    ) -> Result<Pin<&'a mut T>, D::Error> {
+        deserializer.deserialize_struct(T::NAME, T::FIELDS, StructVisitor(ptr.as_mut(), PhantomData))?;
+        Ok(unsafe { Pin::new_unchecked(ptr.get_unchecked_mut().assume_init_mut()) })
+    }
+    struct StructVisitor<'a, 'de, T>(Pin<&'a mut MaybeUninit<T>>, PhantomData<&'de ()>);
+    impl<'de, T: PinVisit<'de>> Visitor<'de> for StructVisitor<'_, 'de, T> {
+        type Value = ();
+
+        fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            write!(f, "struct {}", T::NAME)
+        }
+        fn visit_map<A: MapAccess<'de>>(self, map: A) -> Result<(), A::Error> {
+            T::visit_map(self.0, map)
+        }
+        fn visit_seq<A: SeqAccess<'de>>(self, seq: A) -> Result<(), A::Error> {
+            T::visit_seq(self.0, seq)
+        }
+    }
+    /// Deserializes a field identifier into the matching entry of the field
+    /// names, or `None` for an unknown field.
+    #[derive(Clone, Copy)]
+    pub struct Key(pub &'static [&'static str]);
+    impl<'de> DeserializeSeed<'de> for Key {
+        type Value = Option<&'static str>;
+
+        fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<Self::Value, D::Error> {
+            deserializer.deserialize_identifier(self)
+        }
+    }
+    impl<'de> Visitor<'de> for Key {
+        type Value = Option<&'static str>;
+
+        fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            f.write_str("a field identifier")
+        }
+        fn visit_str<E: Error>(self, value: &str) -> Result<Self::Value, E> {
+            Ok(self.0.iter().copied().find(|&field| field == value))
+        }
+        fn visit_bytes<E: Error>(self, value: &[u8]) -> Result<Self::Value, E> {
+            Ok(self.0.iter().copied().find(|&field| field.as_bytes() == value))
+        }
+        fn visit_u64<E: Error>(self, value: u64) -> Result<Self::Value, E> {
+            Ok(usize::try_from(value).ok().and_then(|index| self.0.get(index).copied()))
+        }
+    }
+    /// Deserializes a [`PinDeserialize`] field in place.
+    pub struct InPlace<'a, T>(pub Pin<&'a mut MaybeUninit<T>>);
+    impl<'de, T: PinDeserialize<'de>> DeserializeSeed<'de> for InPlace<'_, T> {
+        type Value = ();
+
+        fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<(), D::Error> {
+            T::deserialize_pin(self.0, deserializer).map(drop)
+        }
+    }
+}
+/// Drops the first `len` elements starting at `ptr`, unless forgotten.
+struct ElementsGuard<T> {
+    ptr: *mut T,
+    len: usize,
+}
+impl<T> Drop for ElementsGuard<T> {
+    fn drop(&mut self) {
+        unsafe { ptr::drop_in_place(ptr::slice_from_raw_parts_mut(self.ptr, self.len)) };
+    }
+}
+/// Initializes each element of a pinned array in place, passing its index to
+/// `init`, which returns the initialized element. The storage of an element
+/// is only lent to `init` for the call. If `init` panics, the already
+/// initialized elements are dropped.
+///
+/// # Panics
+///
+/// Panics if `init` returns a pointer to another value than the element,
+/// since the element is not initialized then.
+#[track_caller]
+pub fn init_array<'a, T, const N: usize>(
+    ptr: Pin<&'a mut MaybeUninit<[T; N]>>,
+    init: impl for<'s> FnMut(usize, Pin<&'s mut MaybeUninit<T>>) -> Pin<&'s mut T>,
+) -> Pin<&'a mut [T; N]> {
+    unsafe { __init_array(ptr, init) }
+}
+/// Initializes the elements of a pinned array like [`init_array`], but lends
+/// the storage of each element for the whole `'a`, so that the elements may
+/// refer to each other. Used by `pin_array_new!`, `pin_array_new_with!` and
+/// `pin_field_init!`, whose initializer calls can't keep the storage.
+///
+/// # Safety
+///
+/// `init` must not keep the storage of an element after the call, e.g. by
+/// storing its pin, since the element is reachable through the returned pin
+/// afterwards.
+#[doc(hidden)]
+#[track_caller]
+pub unsafe fn __init_array<'a, T, const N: usize>(
+    mut ptr: Pin<&'a mut MaybeUninit<[T; N]>>,
+    mut init: impl FnMut(usize, Pin<&'a mut MaybeUninit<T>>) -> Pin<&'a mut T>,
+) -> Pin<&'a mut [T; N]> {
+    let array_ptr = ptr.as_mut().get_unchecked_mut().as_mut_ptr();
+    let mut guard = ElementsGuard {
+        ptr: array_ptr as *mut T,
+        len: 0,
+    };
+
+    while guard.len < N {
+        let elem_ptr = guard.ptr.add(guard.len);
+        let elem = init(guard.len, Pin::new_unchecked(&mut *(elem_ptr as *mut MaybeUninit<T>)));
+        __assert_init(elem, elem_ptr);
+        guard.len += 1;
+    }
+
+    mem::forget(guard);
+    Pin::new_unchecked(&mut *array_ptr)
+}
+/// Owns a heap allocated `MaybeUninit<T>`, freeing it without dropping the
+/// value unless converted into a `Pin<Box<T>>`. Used by `pin_box_new!`.
+#[cfg(feature = "alloc")]
+pub struct UninitBox<T> {
+    ptr: *mut MaybeUninit<T>,
+}
+#[cfg(feature = "alloc")]
+impl<T> UninitBox<T> {
+    pub fn new() -> Self {
+        Self {
+            ptr: Box::into_raw(Box::new(MaybeUninit::uninit())),
+        }
+    }
+    /// Gets the address the value has once initialized.
+    pub fn as_ptr(&self) -> *const T {
+        self.ptr as *const T
+    }
+    /// # Safety
+    ///
+    /// The returned pointer must not outlive `self`.
+    pub unsafe fn as_uninit_pin<'a>(&self) -> Pin<&'a mut MaybeUninit<T>> {
+        Pin::new_unchecked(&mut *self.ptr)
+    }
+    /// # Safety
+    ///
+    /// The value must be fully initialized.
+    pub unsafe fn assume_init(self) -> Pin<Box<T>> {
+        let ptr = self.ptr as *mut T;
+        mem::forget(self);
+
+        Pin::new_unchecked(Box::from_raw(ptr))
+    }
+}
+#[cfg(feature = "alloc")]
+impl<T> Default for UninitBox<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+#[cfg(feature = "alloc")]
+impl<T> Drop for UninitBox<T> {
+    fn drop(&mut self) {
+        drop(unsafe { Box::from_raw(self.ptr) });
+    }
+}
+/// Owned uninitialized storage, which may be handed through any number of
+/// functions before the value is initialized in it by [`finalize`]. The
+/// storage is moved to the heap once, when the wrapper is created, so its
+/// address is known up front and stays the same after `finalize`, e.g. to
+/// register the value before it exists. Dropping the wrapper frees the storage
+/// without dropping a value.
+///
+/// [`finalize`]: PinUninitOwned::finalize
+/// ```
+/// # use std::{marker::PhantomPinned, mem::MaybeUninit, pin::Pin};
+/// # use pin_macros::{pin_init, PinUninitOwned};
+/// struct Timer {
+///     deadline: u64,
+///     marker: PhantomPinned,
+/// }
+/// impl<'a> Timer {
+///     pin_init!(fn init<'a>(this, deadline: u64) {
+///         this.deadline = deadline;
+///         this.marker = PhantomPinned;
+///     });
+/// }
+///
+/// struct Wheel {
+///     slots: Vec<*const Timer>,
+///     timers: Vec<Pin<Box<Timer>>>,
+/// }
+/// // The container owns the storage and passes the ownership on.
+/// fn reserve(wheel: &mut Wheel) -> PinUninitOwned<Timer> {
+///     let owned = PinUninitOwned::new(MaybeUninit::uninit());
+///     wheel.slots.push(owned.as_ptr());
+///     owned
+/// }
+/// fn schedule(wheel: &mut Wheel, owned: PinUninitOwned<Timer>, deadline: u64) {
+///     wheel.timers.push(owned.finalize(|ptr| Timer::init(ptr, deadline)));
+/// }
+///
+/// let mut wheel = Wheel { slots: Vec::new(), timers: Vec::new() };
+/// let owned = reserve(&mut wheel);
+/// schedule(&mut wheel, owned, 30);
+/// assert_eq!(wheel.timers[0].deadline, 30);
+/// assert_eq!(wheel.slots[0], &*wheel.timers[0] as *const Timer);
+/// ```
+#[cfg(feature = "alloc")]
+pub struct PinUninitOwned<T> {
+    storage: UninitBox<T>,
+}
+#[cfg(feature = "alloc")]
+impl<T> PinUninitOwned<T> {
+    pub fn new(storage: MaybeUninit<T>) -> Self {
+        Self::from_box(Box::new(storage))
+    }
+    /// Takes the ownership of storage already allocated by a container.
+    pub fn from_box(storage: Box<MaybeUninit<T>>) -> Self {
+        Self {
+            storage: UninitBox {
+                ptr: Box::into_raw(storage),
+            },
+        }
+    }
+    /// Gets the address the value will have once finalized.
+    pub fn as_ptr(&self) -> *const T {
+        self.storage.ptr as *const T
+    }
+    /// Initializes the value in the storage, e.g. by `|ptr| T::init(ptr, ...)`,
+    /// and pins it in place. If `init` panics, the storage is freed without
+    /// dropping the value.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `init` returns a pointer to anything but the storage, since
+    /// the storage is not initialized then.
+    #[track_caller]
+    pub fn finalize(self, init: impl FnOnce(Pin<&mut MaybeUninit<T>>) -> Pin<&mut T>) -> Pin<Box<T>> {
+        __assert_init(init(unsafe { self.storage.as_uninit_pin() }), self.as_ptr());
+
+        unsafe { self.storage.assume_init() }
+    }
+}
+/// Frees a heap allocated slice without dropping its elements, unless
+/// forgotten.
+#[cfg(feature = "alloc")]
+struct SliceAllocGuard<T> {
+    ptr: *mut [MaybeUninit<T>],
+}
+#[cfg(feature = "alloc")]
+impl<T> Drop for SliceAllocGuard<T> {
+    fn drop(&mut self) {
+        drop(unsafe { Box::from_raw(self.ptr) });
+    }
+}
+/// Allocates a slice of `len` elements on heap and initializes each of them
+/// in place, passing its index to `init`, which returns the initialized
+/// element. The storage of an element is only lent to `init` for the call. If
+/// `init` panics, the already initialized elements are dropped and the
+/// allocation is freed. Used by `pin_boxed_slice_new!`.
+///
+/// # Panics
+///
+/// Panics if `init` returns a pointer to anything but the storage of the
+/// element, which is not initialized then.
+#[cfg(feature = "alloc")]
+#[track_caller]
+pub fn init_boxed_slice<T>(
+    len: usize,
+    mut init: impl for<'s> FnMut(usize, Pin<&'s mut MaybeUninit<T>>) -> Pin<&'s mut T>,
+) -> Pin<Box<[T]>> {
+    let alloc = SliceAllocGuard {
+        ptr: Box::into_raw(Box::<[MaybeUninit<T>]>::new_uninit_slice(len)),
+    };
+    let mut guard = ElementsGuard {
+        ptr: alloc.ptr as *mut T,
+        len: 0,
+    };
+
+    while guard.len < len {
+        let elem_ptr = unsafe { guard.ptr.add(guard.len) };
+        let elem = init(guard.len, unsafe { Pin::new_unchecked(&mut *(elem_ptr as *mut MaybeUninit<T>)) });
+        __assert_init(elem, elem_ptr);
+        guard.len += 1;
+    }
+
+    mem::forget(guard);
+    let slice_ptr = alloc.ptr as *mut [T];
+    mem::forget(alloc);
+    unsafe { Pin::new_unchecked(Box::from_raw(slice_ptr)) }
+}
+/// Iterator over the elements of a pinned slice, yielding `Pin<&mut T>`, e.g.
+/// of a slice obtained by a `field_pin_slice!` getter. The elements are
+/// pinned as well, since they can't be moved without moving the slice.
+/// ```
+/// # use std::{future::Future, marker::PhantomPinned, pin::{pin, Pin}, task::{Context, Poll, Waker}};
+/// # use pin_macros::{field_pin_slice, PinSliceIterMut};
+/// struct Task {
+///     polls: u32,
+///     marker: PhantomPinned,
+/// }
+/// impl Future for Task {
+///     type Output = ();
+///
+///     fn poll(self: Pin<&mut Self>, _: &mut Context) -> Poll<()> {
+///         unsafe { self.get_unchecked_mut() }.polls += 1;
+///         Poll::Ready(())
+///     }
+/// }
+/// struct Pool {
+///     tasks: [Task; 3],
+/// }
+/// impl Pool {
+///     field_pin_slice!(tasks: Task);
+/// }
+///
+/// let task = || Task { polls: 0, marker: PhantomPinned };
+/// let mut pool = pin!(Pool { tasks: [task(), task(), task()] });
+/// let mut cx = Context::from_waker(Waker::noop());
+/// for task in PinSliceIterMut::new(pool.as_mut().tasks()) {
+///     assert!(task.poll(&mut cx).is_ready());
+/// }
+/// assert!(pool.tasks.iter().all(|task| task.polls == 1));
+/// ```
+pub struct PinSliceIterMut<'a, T> {
+    inner: core::slice::IterMut<'a, T>,
+}
+impl<'a, T> PinSliceIterMut<'a, T> {
+    pub fn new(slice: Pin<&'a mut [T]>) -> Self {
+        Self { inner: unsafe { slice.get_unchecked_mut() }.iter_mut() }
+    }
+}
+impl<'a, T> Iterator for PinSliceIterMut<'a, T> {
+    type Item = Pin<&'a mut T>;
+
+    fn next(&mut self) -> Option<Pin<&'a mut T>> {
+        self.inner.next().map(|elem| unsafe { Pin::new_unchecked(elem) })
+    }
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.inner.size_hint()
+    }
+}
+impl<'a, T> DoubleEndedIterator for PinSliceIterMut<'a, T> {
+    fn next_back(&mut self) -> Option<Pin<&'a mut T>> {
+        self.inner.next_back().map(|elem| unsafe { Pin::new_unchecked(elem) })
+    }
+}
+impl<T> ExactSizeIterator for PinSliceIterMut<'_, T> {}
+impl<T> core::iter::FusedIterator for PinSliceIterMut<'_, T> {}
+/// Recovers the concrete type of a pinned `dyn Any`. Used by
+/// `field_pin_downcast!`.
+pub fn downcast_pin<T: core::any::Any>(ptr: Pin<&mut dyn core::any::Any>) -> Option<Pin<&mut T>> {
+    // The value is only viewed as its concrete type, it stays in place.
+    unsafe { ptr.get_unchecked_mut() }
+        .downcast_mut()
+        .map(|value| unsafe { Pin::new_unchecked(value) })
+}
+/// Field types `field_pin_downcast!` accepts: `dyn Any` optionally with `Send`
+/// and `Sync`, and pinned pointers to them, e.g. `Pin<Box<dyn Any>>`.
+#[doc(hidden)]
+pub trait __PinAny {
+    fn __pin_any(self: Pin<&mut Self>) -> Pin<&mut dyn core::any::Any>;
+}
+impl __PinAny for dyn core::any::Any {
+    fn __pin_any(self: Pin<&mut Self>) -> Pin<&mut dyn core::any::Any> {
+        self
+    }
+}
+impl __PinAny for dyn core::any::Any + Send {
+    fn __pin_any(self: Pin<&mut Self>) -> Pin<&mut dyn core::any::Any> {
+        unsafe { self.map_unchecked_mut(|value| value as &mut dyn core::any::Any) }
+    }
+}
+impl __PinAny for dyn core::any::Any + Send + Sync {
+    fn __pin_any(self: Pin<&mut Self>) -> Pin<&mut dyn core::any::Any> {
+        unsafe { self.map_unchecked_mut(|value| value as &mut dyn core::any::Any) }
+    }
+}
+impl<P: core::ops::DerefMut> __PinAny for Pin<P>
+where
+    P::Target: __PinAny,
+{
+    fn __pin_any(self: Pin<&mut Self>) -> Pin<&mut dyn core::any::Any> {
+        // The pointer is not moved out, only the pointee is reborrowed.
+        unsafe { self.get_unchecked_mut() }.as_mut().__pin_any()
+    }
+}
+/// Lazily initialized immovable value of each thread, defined by
+/// `pin_thread_local!`. The value is initialized in place on the first access
+/// of a thread, and dropped in place when the thread exits, so it is never
+/// moved in between.
+#[cfg(feature = "std")]
+pub struct PinThreadLocal<T: 'static> {
+    key: &'static std::thread::LocalKey<__PinLocalCell<T>>,
+    init: fn(Pin<&mut MaybeUninit<T>>) -> Pin<&mut T>,
+}
+#[cfg(feature = "std")]
+impl<T: 'static> PinThreadLocal<T> {
+    #[doc(hidden)]
+    pub const fn __new(
+        key: &'static std::thread::LocalKey<__PinLocalCell<T>>,
+        init: fn(Pin<&mut MaybeUninit<T>>) -> Pin<&mut T>,
+    ) -> Self {
+        Self { key, init }
+    }
+    /// Calls the closure with the value of the current thread, initializing
+    /// it first if needed.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the value is already accessed, e.g. by the initializer or
+    /// by an outer `with` call, if the thread-local storage is already
+    /// destroyed, or if the initializer returns another pin than the one of
+    /// the value, which is left uninitialized then.
+    pub fn with<R>(&'static self, f: impl FnOnce(Pin<&mut T>) -> R) -> R {
+        self.key.with(|cell| {
+            let mut value = cell.value.try_borrow_mut().expect("the pinned thread-local is already accessed");
+            if !cell.initialized.get() {
+                let value_ptr = value.as_ptr();
+                __assert_init((self.init)(unsafe { Pin::new_unchecked(&mut *value) }), value_ptr);
+                cell.initialized.set(true);
+            }
+            f(unsafe { Pin::new_unchecked(value.assume_init_mut()) })
+        })
+    }
+    /// Calls the closure with a shared pointer to the value of the current
+    /// thread, initializing it first if needed. Shared accesses may be
+    /// nested.
+    ///
+    /// # Panics
+    ///
+    /// Panics under the same conditions as [`PinThreadLocal::with`], except
+    /// that an outer `with_ref` call is allowed.
+    pub fn with_ref<R>(&'static self, f: impl FnOnce(Pin<&T>) -> R) -> R {
+        self.key.with(|cell| {
+            if !cell.initialized.get() {
+                self.with(|_| ());
+            }
+            let value = cell.value.try_borrow().expect("the pinned thread-local is already accessed");
+            f(unsafe { Pin::new_unchecked(value.assume_init_ref()) })
+        })
+    }
+}
+/// Storage of a [`PinThreadLocal`] value.
+#[cfg(feature = "std")]
+#[doc(hidden)]
+pub struct __PinLocalCell<T> {
+    initialized: core::cell::Cell<bool>,
+    value: core::cell::RefCell<MaybeUninit<T>>,
+}
+#[cfg(feature = "std")]
+impl<T> __PinLocalCell<T> {
+    pub const fn uninit() -> Self {
+        Self {
+            initialized: core::cell::Cell::new(false),
+            value: core::cell::RefCell::new(MaybeUninit::uninit()),
+        }
+    }
+}
+#[cfg(feature = "std")]
+impl<T> Drop for __PinLocalCell<T> {
+    fn drop(&mut self) {
+        if self.initialized.get() {
+            unsafe { self.value.get_mut().assume_init_drop() };
+        }
+    }
+}
+
+/// An optional value, which may be initialized in place after the primary
+/// initialization of its owner, see `pin_field_init!`. Unlike `Option<T>`,
+/// becoming `Some` does not require an already existing `T`.
+pub struct PinOption<T> {
+    is_some: bool,
+    value: MaybeUninit<T>,
+}
+impl<T> PinOption<T> {
+    pub const fn none() -> Self {
+        Self {
+            is_some: false,
+            value: MaybeUninit::uninit(),
+        }
+    }
+    pub const fn some(value: T) -> Self {
+        Self {
+            is_some: true,
+            value: MaybeUninit::new(value),
+        }
+    }
+    pub fn is_some(&self) -> bool {
+        self.is_some
+    }
+    pub fn is_none(&self) -> bool {
+        !self.is_some
+    }
+    pub fn as_ref(&self) -> Option<&T> {
+        self.is_some.then(|| unsafe { self.value.assume_init_ref() })
+    }
+    pub fn as_pin_ref(self: Pin<&Self>) -> Option<Pin<&T>> {
+        let this = self.get_ref();
+        this.as_ref().map(|value| unsafe { Pin::new_unchecked(value) })
+    }
+    pub fn as_pin_mut(self: Pin<&mut Self>) -> Option<Pin<&mut T>> {
+        let this = unsafe { self.get_unchecked_mut() };
+        this.is_some
+            .then(|| unsafe { Pin::new_unchecked(this.value.assume_init_mut()) })
+    }
+    /// Drops the value in place, if any, leaving `None`. The option becomes
+    /// `None` before the value is dropped, so a panicking destructor does not
+    /// lead to a double drop.
+    pub fn clear(self: Pin<&mut Self>) {
+        let this = unsafe { self.get_unchecked_mut() };
+
+        if mem::replace(&mut this.is_some, false) {
+            unsafe { this.value.assume_init_drop() };
+        }
+    }
+    /// Gets a raw pointer to the storage of the value, valid even if `None`,
+    /// e.g. to let the elements of an array field point at each other.
+    pub fn as_ptr(&self) -> *const T {
+        self.value.as_ptr()
+    }
+    /// Gets the storage of the value. If the value is already initialized, it
+    /// is forgotten.
+    ///
+    /// # Safety
+    ///
+    /// The value must be fully initialized through the returned pointer
+    /// before calling [`PinOption::assume_some`].
+    pub unsafe fn uninit_ptr(self: Pin<&mut Self>) -> Pin<&mut MaybeUninit<T>> {
+        let this = self.get_unchecked_mut();
+        this.is_some = false;
+
+        Pin::new_unchecked(&mut this.value)
+    }
+    /// # Safety
+    ///
+    /// The value must be fully initialized.
+    pub unsafe fn assume_some(self: Pin<&mut Self>) {
+        self.get_unchecked_mut().is_some = true;
+    }
+}
+impl<T> Default for PinOption<T> {
+    fn default() -> Self {
+        Self::none()
+    }
+}
+impl<T> Drop for PinOption<T> {
+    fn drop(&mut self) {
+        if self.is_some {
+            unsafe { self.value.assume_init_drop() };
+        }
+    }
+}
+/// Polls the stream if `Some`, ending immediately otherwise, so a deferred
+/// stream field may be polled through its pinned getter before being
+/// initialized.
+/// ```
+/// # use std::{marker::PhantomPinned, pin::Pin, task::{Context, Poll, Waker}};
+/// # use futures_core::Stream;
+/// # use pin_macros::{field_pin, field_unpin, pin_field_init, pin_init, pin_new, PinOption};
+/// // Yields the words of its own buffer.
+/// struct Words<'a> {
+///     text: String,
+///     rest: &'a str,
+///     marker: PhantomPinned,
+/// }
+/// impl<'a> Words<'a> {
+///     pin_init!(fn init<'a>(this, text: &str) {
+///         pin_init_write!(text = String::from(text));
+///         this.rest = unsafe { &*(this.text.as_str() as *const str) };
+///         this.marker = PhantomPinned;
+///     });
+///     field_unpin!(rest: &'a str);
+/// }
+/// impl<'a> Stream for Words<'a> {
+///     type Item = &'a str;
+///
+///     fn poll_next(self: Pin<&mut Self>, _: &mut Context) -> Poll<Option<&'a str>> {
+///         let rest = self.rest();
+///         let (word, tail) = rest.split_once(' ').unwrap_or((rest, ""));
+///         *rest = tail;
+///         Poll::Ready((!word.is_empty()).then_some(word))
+///     }
+/// }
+///
+/// struct Reader<'a> {
+///     words: PinOption<Words<'a>>,
+/// }
+/// impl<'a> Reader<'a> {
+///     pin_init!(fn init<'a>(this) {
+///         pin_init_write!(words = PinOption::none());
+///     });
+///     fn open(mut self: Pin<&mut Self>, text: &str) {
+///         pin_field_init!(Words: init(self.words, text));
+///     }
+///     field_pin!(words: PinOption<Words<'a>>);
+/// }
+/// impl<'a> Stream for Reader<'a> {
+///     type Item = &'a str;
+///
+///     fn poll_next(self: Pin<&mut Self>, cx: &mut Context) -> Poll<Option<&'a str>> {
+///         self.words().poll_next(cx)
+///     }
+/// }
+///
+/// let mut cx = Context::from_waker(Waker::noop());
+/// pin_new!(mut reader: Reader = init());
+/// assert_eq!(reader.as_mut().poll_next(&mut cx), Poll::Ready(None));
+/// reader.as_mut().open("pinned words");
+/// assert_eq!(reader.as_mut().poll_next(&mut cx), Poll::Ready(Some("pinned")));
+/// assert_eq!(reader.as_mut().poll_next(&mut cx), Poll::Ready(Some("words")));
+/// assert_eq!(reader.as_mut().poll_next(&mut cx), Poll::Ready(None));
+/// ```
+#[cfg(feature = "stream")]
+impl<T: futures_core::Stream> futures_core::Stream for PinOption<T> {
+    type Item = T::Item;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut core::task::Context) -> core::task::Poll<Option<T::Item>> {
+        match self.as_pin_mut() {
+            Some(stream) => stream.poll_next(cx),
+            None => core::task::Poll::Ready(None),
+        }
+    }
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.as_ref().map_or((0, Some(0)), T::size_hint)
+    }
+}
+/// Clears a [`PinOption`] on drop, unless disarmed through the shared flag.
+/// Used by `pin_fields_init!` to undo the already initialized fields.
+#[doc(hidden)]
+pub struct __ClearGuard<'a, T> {
+    armed: &'a core::cell::Cell<bool>,
+    option: *mut PinOption<T>,
+}
+impl<'a, T> __ClearGuard<'a, T> {
+    /// # Safety
+    ///
+    /// `option` must stay valid and pinned until the guard is dropped.
+    pub unsafe fn new(armed: &'a core::cell::Cell<bool>, option: *mut PinOption<T>) -> Self {
+        Self { armed, option }
+    }
+}
+impl<T> Drop for __ClearGuard<'_, T> {
+    fn drop(&mut self) {
+        if self.armed.get() {
+            unsafe { Pin::new_unchecked(&mut *self.option) }.clear();
+        }
+    }
+}
+/// Replaces the value of an `Option` field, which must be `Unpin`, since the
+/// old value is moved out. Used by `pin_field_set!`.
+#[doc(hidden)]
+pub fn __replace_unpin<T: Unpin>(option: &mut Option<T>, value: T) -> Option<T> {
+    option.replace(value)
+}
+/// Writes a `Copy` value without dropping the previous one. Used by
+/// `pin_write_fields!`, so only fields without drop glue are written.
+///
+/// # Safety
+///
+/// `ptr` must be valid for writes.
+#[doc(hidden)]
+pub unsafe fn __write_copy<T: Copy>(ptr: *mut T, value: T) {
+    ptr::write(ptr, value);
+}
+/// A field recording its own address, written by `pin_addr_guard!`. With the
+/// `test-pin-invariants` feature, [`assert_pinned`] panics once the value
+/// holding the field has been moved, e.g. by unsound code reading it out of
+/// its storage. Otherwise the field is zero-sized and the check compiles out.
+///
+/// [`assert_pinned`]: PinAddr::assert_pinned
+/// ```
+/// # use std::{marker::PhantomPinned, pin::Pin};
+/// # use pin_macros::{pin_init, pin_new, PinAddr};
+/// struct Tracked {
+///     value: u32,
+///     addr: PinAddr,
+///     marker: PhantomPinned,
+/// }
+/// impl<'a> Tracked {
+///     pin_init!(fn init<'a>(this, value: u32) {
+///         this.value = value;
+///         pin_addr_guard!(addr);
+///         this.marker = PhantomPinned;
+///     });
+///     fn value(self: Pin<&Self>) -> u32 {
+///         self.addr.assert_pinned();
+///         self.value
+///     }
+/// }
+///
+/// pin_new!(tracked: Tracked = init(7));
+/// assert_eq!(tracked.as_ref().value(), 7);
+///
+/// // Unsound: the pinned value is moved out of its storage.
+/// let mut moved = unsafe { std::ptr::read(&*tracked) };
+/// let moved = unsafe { Pin::new_unchecked(&mut moved) };
+/// let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| moved.as_ref().value()));
+/// # #[cfg(feature = "test-pin-invariants")]
+/// assert!(result.is_err());
+/// # #[cfg(not(feature = "test-pin-invariants"))]
+/// # assert!(result.is_ok());
+/// ```
+pub struct PinAddr {
+    #[cfg(feature = "test-pin-invariants")]
+    addr: usize,
+}
+impl PinAddr {
+    #[doc(hidden)]
+    #[allow(unused_variables)]
+    pub fn __new(field: *const Self) -> Self {
+        Self {
+            #[cfg(feature = "test-pin-invariants")]
+            addr: field as usize,
+        }
+    }
+    /// Asserts that the field is still at the address it was written to.
+    #[track_caller]
+    #[inline]
+    pub fn assert_pinned(&self) {
+        #[cfg(feature = "test-pin-invariants")]
+        if self.addr != self as *const Self as usize {
+            panic!("the pinned value was moved from {:#x} to {:p}", self.addr, self);
+        }
+    }
+}
+/// The source slice of `pin_field_copy_slice!` differs in length from the
+/// field.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SliceLenError {
+    /// The length of the field.
+    pub expected: usize,
+    /// The length of the source slice.
+    pub actual: usize,
+}
+impl core::fmt::Display for SliceLenError {
+    fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+        write!(f, "expected {} elements, got {}", self.expected, self.actual)
+    }
+}
+#[cfg(feature = "std")]
+impl std::error::Error for SliceLenError {}
+/// Copies the whole slice at once, if the lengths match. Used by
+/// `pin_field_copy_slice!`.
+#[doc(hidden)]
+pub fn __copy_slice<T: Copy>(dst: &mut [T], src: &[T]) -> Result<(), SliceLenError> {
+    if dst.len() != src.len() {
+        return Err(SliceLenError { expected: dst.len(), actual: src.len() });
+    }
+
+    dst.copy_from_slice(src);
+    Ok(())
+}
+/// Ambiguous for `Unpin` types, since both of the impls apply. Used by
+/// `pin_assert_immovable!`, `pin_intrusive_node!` and `#[pin(check)]` of the
+/// `PinProject` derive.
+#[doc(hidden)]
+pub trait __MustNotBeUnpin<A> {
+    fn check() {}
+}
+impl<T: ?Sized> __MustNotBeUnpin<()> for T {}
+impl<T: ?Sized + Unpin> __MustNotBeUnpin<u8> for T {}
+
+/// Asserts at compile time that the types are not `Unpin`, e.g. that an
+/// immovable type was not left without a `PhantomPinned` field. Otherwise
+/// the compilation fails with "type annotations needed", pointing at the
+/// `Unpin` type. Generic types are checked by their instantiations.
+/// ```compile_fail
+/// # use pin_macros::pin_assert_immovable;
+/// struct Node {
+///     next: Option<&'static Node>,
+/// }
+/// pin_assert_immovable!(Node);
+/// ```
+#[macro_export]
+macro_rules! pin_assert_immovable {
+    ($($type:ty),+ $(,)?) => {
+        const _: () = {
+            #[allow(dead_code)]
+            fn check() {
+                $(let _ = <$type as $crate::__MustNotBeUnpin<_>>::check;)+
+            }
+        };
+    };
+}
+/// Declares a struct with named fields, appending a private
+/// `__pinned: PhantomPinned` field, so the struct can't accidentally be left
+/// `Unpin`. The marker is a ZST, hence `pin_init!` methods don't have to
+/// initialize it, while struct expressions have to mention it. The field
+/// layout is also exposed to the `debug-init` checks, so they can point at
+/// any forgotten field.
+/// ```
+/// # use std::{marker::PhantomPinned, pin::Pin};
+/// # use pin_macros::{pin_assert_immovable, pin_init, pin_new, pin_struct};
+/// pin_struct! {
+///     pub struct Node<'a> {
+///         pub value: u32,
+///         this: Option<&'a Node<'a>>,
+///     }
+/// }
+/// impl<'a> Node<'a> {
+///     pin_init!(fn init<'a>(this, value: u32) {
+///         this.value = value;
+///         this.this = None;
+///     });
+/// }
+/// pin_assert_immovable!(Node<'_>);
+///
+/// pin_new!(node: Node = init(1));
+/// assert_eq!(node.value, 1);
+/// ```
+#[macro_export]
+macro_rules! pin_struct {
+    ($(#[$attr:meta])* $v:vis struct $name:ident<$($rest:tt)+) => {
+        $crate::pin_struct!(@param {[$(#[$attr])* $v] $name} [] [] [] $($rest)+);
+    };
+    ($(#[$attr:meta])* $v:vis struct $name:ident $($rest:tt)+) => {
+        $crate::pin_struct!(@where {[$(#[$attr])* $v] $name} [] [] [] {} $($rest)+);
+    };
+    // Splits the generic parameters into the declared ones, the `impl` ones
+    // (without defaults) and the type arguments.
+    (@param $head:tt $gen:tt $impl:tt $args:tt > $($rest:tt)+) => {
+        $crate::pin_struct!(@where $head $gen $impl $args {} $($rest)+);
+    };
+    (@param $head:tt [$($gen:tt)*] [$($impl:tt)*] [$($args:tt)*] $a:lifetime $($rest:tt)+) => {
+        $crate::pin_struct!(@bound $head [$($gen)* $a] [$($impl)* $a] [$($args)* $a,] [] $($rest)+);
+    };
+    (@param $head:tt [$($gen:tt)*] [$($impl:tt)*] [$($args:tt)*] const $n:ident $($rest:tt)+) => {
+        $crate::pin_struct!(@bound $head [$($gen)* const $n] [$($impl)* const $n] [$($args)* $n,] [] $($rest)+);
+    };
+    (@param $head:tt [$($gen:tt)*] [$($impl:tt)*] [$($args:tt)*] $t:ident $($rest:tt)+) => {
+        $crate::pin_struct!(@bound $head [$($gen)* $t] [$($impl)* $t] [$($args)* $t,] [] $($rest)+);
+    };
+    (@bound $head:tt [$($gen:tt)*] [$($impl:tt)*] $args:tt [] , $($rest:tt)+) => {
+        $crate::pin_struct!(@param $head [$($gen)* ,] [$($impl)* ,] $args $($rest)+);
+    };
+    (@bound $head:tt $gen:tt $impl:tt $args:tt [] > $($rest:tt)+) => {
+        $crate::pin_struct!(@where $head $gen $impl $args {} $($rest)+);
+    };
+    (@bound $head:tt [$($gen:tt)*] [$($impl:tt)*] $args:tt [<] >> $($rest:tt)+) => {
+        $crate::pin_struct!(@where $head [$($gen)* >] [$($impl)* >] $args {} $($rest)+);
+    };
+    (@bound $head:tt [$($gen:tt)*] $impl:tt $args:tt [] = $($rest:tt)+) => {
+        $crate::pin_struct!(@default $head [$($gen)* =] $impl $args [] $($rest)+);
+    };
+    (@bound $head:tt [$($gen:tt)*] [$($impl:tt)*] $args:tt [$($depth:tt)*] < $($rest:tt)+) => {
+        $crate::pin_struct!(@bound $head [$($gen)* <] [$($impl)* <] $args [$($depth)* <] $($rest)+);
+    };
+    (@bound $head:tt [$($gen:tt)*] [$($impl:tt)*] $args:tt [< $($depth:tt)*] > $($rest:tt)+) => {
+        $crate::pin_struct!(@bound $head [$($gen)* >] [$($impl)* >] $args [$($depth)*] $($rest)+);
+    };
+    (@bound $head:tt [$($gen:tt)*] [$($impl:tt)*] $args:tt [< < $($depth:tt)*] >> $($rest:tt)+) => {
+        $crate::pin_struct!(@bound $head [$($gen)* >>] [$($impl)* >>] $args [$($depth)*] $($rest)+);
+    };
+    (@bound $head:tt [$($gen:tt)*] [$($impl:tt)*] $args:tt $depth:tt $t:tt $($rest:tt)+) => {
+        $crate::pin_struct!(@bound $head [$($gen)* $t] [$($impl)* $t] $args $depth $($rest)+);
+    };
+    (@default $head:tt [$($gen:tt)*] [$($impl:tt)*] $args:tt [] , $($rest:tt)+) => {
+        $crate::pin_struct!(@param $head [$($gen)* ,] [$($impl)* ,] $args $($rest)+);
+    };
+    (@default $head:tt $gen:tt $impl:tt $args:tt [] > $($rest:tt)+) => {
+        $crate::pin_struct!(@where $head $gen $impl $args {} $($rest)+);
+    };
+    (@default $head:tt [$($gen:tt)*] $impl:tt $args:tt [<] >> $($rest:tt)+) => {
+        $crate::pin_struct!(@where $head [$($gen)* >] $impl $args {} $($rest)+);
+    };
+    (@default $head:tt [$($gen:tt)*] $impl:tt $args:tt [$($depth:tt)*] < $($rest:tt)+) => {
+        $crate::pin_struct!(@default $head [$($gen)* <] $impl $args [$($depth)* <] $($rest)+);
+    };
+    (@default $head:tt [$($gen:tt)*] $impl:tt $args:tt [< $($depth:tt)*] > $($rest:tt)+) => {
+        $crate::pin_struct!(@default $head [$($gen)* >] $impl $args [$($depth)*] $($rest)+);
+    };
+    (@default $head:tt [$($gen:tt)*] $impl:tt $args:tt [< < $($depth:tt)*] >> $($rest:tt)+) => {
+        $crate::pin_struct!(@default $head [$($gen)* >>] $impl $args [$($depth)*] $($rest)+);
+    };
+    (@default $head:tt [$($gen:tt)*] $impl:tt $args:tt $depth:tt $t:tt $($rest:tt)+) => {
+        $crate::pin_struct!(@default $head [$($gen)* $t] $impl $args $depth $($rest)+);
+    };
+    (@where {[$($head:tt)*] $name:ident} [$($gen:tt)*] [$($impl:tt)*] [$($args:tt)*] {$($where:tt)*} {
+        $($(#[$fattr:meta])* $fv:vis $fieldn:ident: $fieldt:ty),* $(,)?
+    }) => {
+        $($head)* struct $name<$($gen)*> $($where)* {
+            $($(#[$fattr])* $fv $fieldn: $fieldt,)*
+            __pinned: core::marker::PhantomPinned,
+        }
+        impl<$($impl)*> $crate::__PinFields for $name<$($args)*> $($where)* {
+            fn fields(ptr: *mut Self, field: &mut dyn FnMut(usize, usize, bool)) {
+                $($crate::__pin_field(ptr, unsafe { core::ptr::addr_of_mut!((*ptr).$fieldn) }, {
+                    use $crate::{__ProbeBytes as _, __ProbeNoBytes as _};
+                    (&$crate::__BytesProbe::<$fieldt>(core::marker::PhantomData)).readable()
+                }, field);)*
+            }
+        }
+    };
+    (@where $head:tt $gen:tt $impl:tt $args:tt {$($where:tt)*} $t:tt $($rest:tt)+) => {
+        $crate::pin_struct!(@where $head $gen $impl $args {$($where)* $t} $($rest)+);
+    };
+}
+/// Implements [`PinBuild`] for a struct, declaring a module of marker types,
+/// one per field and named after it, which [`PinBuilder`] initializes the
+/// fields through. The fields are listed with their types, e.g.
+/// `pin_builder!(pub mod node for Node<'a> { value: u32, inner: Inner<'a> })`,
+/// and all of them must be listed. Zero-sized fields, like `PhantomPinned`,
+/// may be left out then.
+/// ```
+/// # use std::marker::PhantomPinned;
+/// # use pin_macros::{pin_builder, pin_init, uninit_pin, PinBuilder};
+/// struct Inner {
+///     value: u32,
+///     marker: PhantomPinned,
+/// }
+/// impl<'a> Inner {
+///     pin_init!(fn init<'a>(this, value: u32) {
+///         this.value = value;
+///     });
+/// }
+/// struct Node<'a> {
+///     name: &'a str,
+///     inner: Inner,
+///     this: *const Node<'a>,
+/// }
+/// pin_builder!(mod node for Node<'a> { name: &'a str, inner: Inner, this: *const Node<'a> });
+///
+/// uninit_pin!(storage: Node);
+/// let builder = PinBuilder::new(storage).set(node::name, "root");
+/// let this = builder.as_mut_ptr();
+/// let node = builder.set(node::this, this).init(node::inner, |ptr| Inner::init(ptr, 1)).finish();
+/// assert_eq!((node.name, node.inner.value), ("root", 1));
+/// assert!(std::ptr::eq(node.this, &*node));
+/// ```
+#[macro_export]
+macro_rules! pin_builder {
+    ($v:vis mod $module:ident for $name:ident$(<$($a:lifetime),+>)? { $($fieldn:ident: $fieldt:ty),* $(,)? }) => {
+        #[allow(non_camel_case_types)]
+        $v mod $module {
+            $(pub struct $fieldn;)*
+        }
+        unsafe impl$(<$($a),+>)? $crate::PinBuild<{ 0 $(+ $crate::pin_builder!(@one $fieldn))* }> for $name$(<$($a),+>)? {
+            type Start = ($($crate::pin_builder!(@state $fieldn $crate::FieldUnset),)*);
+            type Done = ($($crate::pin_builder!(@state $fieldn $crate::FieldSet),)*);
+        }
+        $crate::pin_builder!(@field $module $name[$($($a)+)?] [] $($fieldn: $fieldt),*);
+    };
+    (@state $fieldn:ident $state:ty) => {
+        $state
+    };
+    (@one $fieldn:ident) => {
+        1
+    };
+    // Generic over the states of the fields before and after the current one,
+    // named after the fields.
+    (@field $module:ident $name:ident[$($a:lifetime)*] [$($before:ident)*] $fieldn:ident: $fieldt:ty $(, $after:ident: $aftert:ty)*) => {
+        #[allow(non_camel_case_types)]
+        unsafe impl<$($a,)* $($before,)* $($after,)*> $crate::PinBuildField<$name<$($a),*>, ($($before,)* $crate::FieldUnset, $($after,)*)> for $module::$fieldn {
+            type Type = $fieldt;
+            type Next = ($($before,)* $crate::FieldSet, $($after,)*);
+
+            unsafe fn project(ptr: *mut $name<$($a),*>) -> *mut $fieldt {
+                core::ptr::addr_of_mut!((*ptr).$fieldn)
+            }
+        }
+        $crate::pin_builder!(@field $module $name[$($a)*] [$($before)* $fieldn] $($after: $aftert),*);
+    };
+    (@field $module:ident $name:ident[$($a:lifetime)*] [$($before:ident)*]) => {};
+}
+/// Allocates uninitialized storage on stack, storing
+/// `Pin<&mut MaybeUninit<T>>`, which may be passed to an initializer or
+/// written step by step. The storage is not dropped as `T`.
+///
+/// # Safety
+///
+/// Although the macro is safe, the storage must be fully initialized before
+/// it is treated as `T` (for example, by `assume_init_mut` or by casting the
+/// pointer), and must not be moved out of after that.
+/// ```
+/// # use std::pin::Pin;
+/// # use pin_macros::uninit_pin;
+/// uninit_pin!(mut storage: [u32; 2]);
+/// let array = unsafe { storage.as_mut().get_unchecked_mut() };
+/// array.write([1, 2]);
+/// let array: Pin<&mut [u32; 2]> = unsafe { storage.map_unchecked_mut(|storage| storage.assume_init_mut()) };
+/// assert_eq!(*array, [1, 2]);
+/// ```
+///
+/// With the `legacy-pin` feature, `core::pin::pin!` (Rust 1.68 or newer) is
+/// not used, so the stack macros work on older compilers.
+#[macro_export]
+macro_rules! uninit_pin {
+    ($varn:ident: $vart:ty) => {
+        $crate::__pin_storage!([] $varn: core::mem::MaybeUninit<$vart>);
+    };
+    (mut $varn:ident: $vart:ty) => {
+        $crate::__pin_storage!([mut] $varn: core::mem::MaybeUninit<$vart>);
+    };
+}
+#[cfg(not(feature = "legacy-pin"))]
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __pin_storage {
+    ([$($mut:tt)?] $varn:ident: $vart:ty) => {
+        let $($mut)? $varn = core::pin::pin!(<$vart>::uninit());
+    };
+}
+#[cfg(feature = "legacy-pin")]
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __pin_storage {
+    ([$($mut:tt)?] $varn:ident: $vart:ty) => {
+        let mut __storage = <$vart>::uninit();
+        // The storage can't be named outside of the macro, so it is never
+        // moved after being pinned.
+        let $($mut)? $varn = unsafe { core::pin::Pin::new_unchecked(&mut __storage) };
+    };
+}
+/// Initializes owned immovable value on stack, using the named initializer.
+/// Tokens following the initializer call (`?`, `.await`) are applied to its
+/// result. The initializer may also be given by a path, e.g.
+/// `Pool::init_in(ctx, a)` or `<T as PinInit<_>>::init(args)`, which gets
+/// the storage as its implicit first argument as well, so any function
+/// taking `Pin<&mut MaybeUninit<T>>` first may be used.
+/// ```
+/// # use std::pin::Pin;
+/// # use pin_macros::{pin_init, pin_new};
+/// struct Buffer<'a> {
+///     data: Vec<u8>,
+///     this: Option<&'a Buffer<'a>>,
+/// }
+/// impl<'a> Buffer<'a> {
+///     pin_init!(fn init<'a>(this) {
+///         pin_init_write!(data = Vec::new());
+///         this.this = None;
+///     });
+///     pin_init!(fn with_capacity<'a>(this, capacity: usize) {
+///         pin_init_write!(data = Vec::with_capacity(capacity));
+///         this.this = None;
+///     });
+/// }
+///
+/// pin_new!(empty: Buffer = init());
+/// pin_new!(reserved: Buffer = with_capacity(8));
+/// pin_new!(qualified: Buffer = Buffer::with_capacity(4));
+/// assert_eq!(empty.data.capacity(), 0);
+/// assert!(reserved.data.capacity() >= 8);
+/// assert!(qualified.data.capacity() >= 4);
+/// ```
+///
+/// If the initializer panics, the storage is only ever unwound as raw bytes,
+/// so no destructor of `T` runs. A `pin_init!` initializer drops the fields
+/// it has tracked so far (see `pin_init_write!`), each exactly once, and the
+/// untracked ones are not touched. So the panic may be caught, and the
+/// program carries on in a consistent state.
+/// ```
+/// # use std::{cell::RefCell, panic::{self, AssertUnwindSafe}};
+/// # use pin_macros::{pin_init, pin_new};
+/// struct Noisy<'a>(&'a RefCell<Vec<&'static str>>, &'static str);
+/// impl Drop for Noisy<'_> {
+///     fn drop(&mut self) {
+///         self.0.borrow_mut().push(self.1);
+///     }
+/// }
+/// struct Triple<'a> {
+///     a: Noisy<'a>,
+///     b: Noisy<'a>,
+///     c: Noisy<'a>,
+/// }
+/// impl<'a> Triple<'a> {
+///     pin_init!(fn init<'a>(this, log: &'a RefCell<Vec<&'static str>>, fail: bool) {
+///         pin_init_write!(a = Noisy(log, "a"));
+///         pin_init_write!(b = Noisy(log, "b"));
+///         assert!(!fail, "out of resources");
+///         pin_init_write!(c = Noisy(log, "c"));
+///     });
+/// }
+///
+/// let log = RefCell::new(Vec::new());
+/// let result = panic::catch_unwind(AssertUnwindSafe(|| {
+///     pin_new!(triple: Triple = init(&log, true));
+///     unreachable!("{}", triple.c.1);
+/// }));
+/// assert!(result.is_err());
+/// // Only the written fields are dropped, in reverse order.
+/// assert_eq!(*log.borrow(), ["b", "a"]);
+///
+/// log.borrow_mut().clear();
+/// pin_new!(triple: Triple = init(&log, false));
+/// assert_eq!(triple.c.1, "c");
+/// assert!(log.borrow().is_empty());
+/// ```
+///
+/// An initializer returning extra data along with the pointer, i.e. a
+/// `pin_init!` method with an explicit `-> (Pin<&'a mut Self>, Aux)` return
+/// type, is destructured by a tuple pattern instead of the variable name.
+/// ```
+/// # use std::{marker::PhantomPinned, pin::Pin};
+/// # use pin_macros::{pin_init, pin_new};
+/// struct Pool {
+///     slots: Vec<u32>,
+///     marker: PhantomPinned,
+/// }
+/// impl<'a> Pool {
+///     pin_init!(fn init<'a>(this, hint: usize) -> (Pin<&'a mut Self>, usize) {
+///         pin_init_write!(slots = Vec::with_capacity(hint));
+///         this.marker = PhantomPinned;
+///         let capacity = this.slots.capacity();
+///         (pin_init_clone!(), capacity)
+///     });
+/// }
+///
+/// pin_new!((mut pool, capacity): Pool = init(10));
+/// assert!(capacity >= 10);
+/// // SAFETY: the vector is not moved out of.
+/// unsafe { pool.as_mut().get_unchecked_mut() }.slots.push(1);
+/// assert_eq!(pool.slots, [1]);
+/// ```
+///
+/// A value can't be written in place by a struct expression, so one given
+/// instead of an initializer call fails with a hint to call an initializer
+/// (or `init_from` to move the built value in), as does a missing type.
+/// ```compile_fail
+/// # use std::marker::PhantomPinned;
+/// # use pin_macros::pin_new;
+/// struct Node {
+///     marker: PhantomPinned,
+/// }
+/// pin_new!(node: Node = Node { marker: PhantomPinned });
+/// ```
+#[macro_export]
+macro_rules! pin_new {
+    ($varn:ident: $vart:ty = $head:ident $(:: $seg:ident)+ ($($arg:expr),* $(,)?) $($tail:tt)*) => {
+        $crate::uninit_pin!(__uninit_ptr: $vart);
+        let $varn = $head $(:: $seg)+(__uninit_ptr, $($arg),*) $($tail)*;
+    };
+    (mut $varn:ident: $vart:ty = $head:ident $(:: $seg:ident)+ ($($arg:expr),* $(,)?) $($tail:tt)*) => {
+        $crate::uninit_pin!(__uninit_ptr: $vart);
+        let mut $varn = $head $(:: $seg)+(__uninit_ptr, $($arg),*) $($tail)*;
+    };
+    ($varn:ident: $vart:ty = <$qself:ty as $trait:path>::$methodn:ident($($arg:expr),* $(,)?) $($tail:tt)*) => {
+        $crate::uninit_pin!(__uninit_ptr: $vart);
+        let $varn = <$qself as $trait>::$methodn(__uninit_ptr, $($arg),*) $($tail)*;
+    };
+    (mut $varn:ident: $vart:ty = <$qself:ty as $trait:path>::$methodn:ident($($arg:expr),* $(,)?) $($tail:tt)*) => {
+        $crate::uninit_pin!(__uninit_ptr: $vart);
+        let mut $varn = <$qself as $trait>::$methodn(__uninit_ptr, $($arg),*) $($tail)*;
+    };
+    ($varn:ident: $vart:ty = $methodn:ident($($arg:expr),* $(,)?) $($tail:tt)*) => {
+        $crate::uninit_pin!(__uninit_ptr: $vart);
+        let $varn = <$vart>::$methodn(__uninit_ptr, $($arg),*) $($tail)*;
+    };
+    (mut $varn:ident: $vart:ty = $methodn:ident($($arg:expr),* $(,)?) $($tail:tt)*) => {
+        $crate::uninit_pin!(__uninit_ptr: $vart);
+        let mut $varn = <$vart>::$methodn(__uninit_ptr, $($arg),*) $($tail)*;
+    };
+    (($($pat:tt)*): $vart:ty = $($init:tt)+) => {
+        $crate::pin_new!(__pin_new_result: $vart = $($init)+);
+        let ($($pat)*) = __pin_new_result;
+    };
+    (mut $varn:ident = $($rest:tt)*) => {
+        $crate::pin_new!($varn = $($rest)*);
+    };
+    ($varn:ident = $($rest:tt)*) => {
+        compile_error!("the type of the value must be given, e.g. `pin_new!(node: Node = init())`");
+    };
+    ($($rest:tt)*) => {
+        compile_error!(
+            "expected an initializer call, e.g. `pin_new!(node: Node = init())`, since the value is \
+             initialized in place; an already built value is moved in by `pin_macros::init_from(value)`"
+        );
+    };
+}
+/// Initializes owned immovable value on stack like `pin_new!`, storing a
+/// [`PinDropGuard`] instead of the bare pointer, which drops the value in
+/// place when the guard goes out of scope, or earlier by `drop(guard)`, e.g.
+/// to release a lock before a long operation. All initializer forms of
+/// `pin_new!` are accepted.
+/// ```
+/// # use std::{marker::PhantomPinned, sync::{Mutex, MutexGuard}};
+/// # use pin_macros::{pin_init, pin_new_guarded};
+/// struct Session<'a> {
+///     lock: MutexGuard<'a, u32>,
+///     marker: PhantomPinned,
+/// }
+/// impl<'a> Session<'a> {
+///     pin_init!(fn init<'a>(this, counter: &'a Mutex<u32>) {
+///         pin_init_write!(lock = counter.lock().unwrap());
+///     });
+/// }
+///
+/// let counter = Mutex::new(0);
+/// pin_new_guarded!(session: Session = init(&counter));
+/// assert_eq!(*session.lock, 0);
+/// drop(session);
+/// // The lock is released, while the storage is still in scope.
+/// assert!(counter.try_lock().is_ok());
+/// ```
+#[macro_export]
+macro_rules! pin_new_guarded {
+    ($varn:ident: $vart:ty = $($init:tt)+) => {
+        $crate::pin_new!(__guarded_ptr: $vart = $($init)+);
+        let $varn = unsafe { $crate::PinDropGuard::new(__guarded_ptr) };
+    };
+    (mut $varn:ident: $vart:ty = $($init:tt)+) => {
+        $crate::pin_new!(__guarded_ptr: $vart = $($init)+);
+        let mut $varn = unsafe { $crate::PinDropGuard::new(__guarded_ptr) };
+    };
+}
+/// Initializes owned immovable value in storage provided by the caller, e.g.
+/// a static or a slot of a memory pool, evaluating to `Pin<&mut T>` bound to
+/// the storage borrow. The storage is `&mut MaybeUninit<T>`, which is pinned
+/// and passed as the first argument of the initializer, given by a path, e.g.
+/// `Node::init(args)`.
+///
+/// # Safety
+///
+/// The macro must be used in an `unsafe` block, by which the caller asserts
+/// the pinning contract: after the initialization, the storage must not be
+/// moved out of, overwritten, or freed until the value is dropped in place,
+/// or never, e.g. in a static.
+/// ```
+/// # use std::{marker::PhantomPinned, mem::MaybeUninit, pin::Pin, ptr::{self, addr_of_mut}};
+/// # use pin_macros::pin_init_into;
+/// struct Registry {
+///     this: *const Registry,
+///     marker: PhantomPinned,
+/// }
+/// impl Registry {
+///     fn init(ptr: Pin<&mut MaybeUninit<Self>>) -> Pin<&mut Self> {
+///         let ptr = unsafe { ptr.get_unchecked_mut() };
+///         let this = ptr.as_ptr();
+///         unsafe { Pin::new_unchecked(ptr.write(Self { this, marker: PhantomPinned })) }
+///     }
+/// }
+///
+/// static mut STORAGE: MaybeUninit<Registry> = MaybeUninit::uninit();
+/// // The static is never moved or written again.
+/// let registry: Pin<&'static mut Registry> = unsafe { pin_init_into!(&mut *addr_of_mut!(STORAGE), Registry::init()) };
+/// assert!(ptr::eq(registry.this, &*registry));
+/// ```
+#[macro_export]
+macro_rules! pin_init_into {
+    ($storage:expr, $head:ident $(:: $seg:ident)+ ($($arg:expr),* $(,)?)) => {
+        $head $(:: $seg)+(core::pin::Pin::new_unchecked($storage), $($arg),*)
+    };
+    ($storage:expr, <$qself:ty as $trait:path>::$methodn:ident($($arg:expr),* $(,)?)) => {
+        <$qself as $trait>::$methodn(core::pin::Pin::new_unchecked($storage), $($arg),*)
+    };
+}
+/// Initializes owned immovable value on stack like `pin_new!`, and calls the
+/// closure with the pointer, evaluating to its result. The storage lives
+/// until the closure returns, and is then freed the same way `pin_new!`
+/// storage is at the end of its block.
+/// ```
+/// # use std::{marker::PhantomPinned, mem::MaybeUninit, pin::Pin};
+/// # use pin_macros::with_pin_new;
+/// struct Counter {
+///     count: u32,
+///     marker: PhantomPinned,
+/// }
+/// impl Counter {
+///     fn init(ptr: Pin<&mut MaybeUninit<Self>>, count: u32) -> Pin<&mut Self> {
+///         let ptr = unsafe { ptr.get_unchecked_mut() };
+///         unsafe { Pin::new_unchecked(ptr.write(Self { count, marker: PhantomPinned })) }
+///     }
+/// }
+///
+/// let count = with_pin_new!(Counter = init(2), |counter| counter.count * 2);
+/// assert_eq!(count, 4);
+/// ```
+#[macro_export]
+macro_rules! with_pin_new {
+    ($vart:ty = $methodn:ident($($arg:expr),* $(,)?), $closure:expr) => {{
+        fn __call<'a, T, R>(ptr: core::pin::Pin<&'a mut T>, f: impl FnOnce(core::pin::Pin<&'a mut T>) -> R) -> R {
+            f(ptr)
+        }
+
+        $crate::uninit_pin!(__uninit_ptr: $vart);
+        __call(<$vart>::$methodn(__uninit_ptr, $($arg),*), $closure)
+    }};
+}
+/// Defines a constructor, which initializes owned immovable value on stack
+/// like `with_pin_new!` and passes it to the closure given as its last
+/// argument, returning the closure's result. Since a pinned value can't be
+/// returned from the frame owning its storage, this is the way to factor its
+/// construction out. The lifetime after the name is the storage lifetime,
+/// which the closure is generic over.
+/// ```
+/// # use std::pin::Pin;
+/// # use pin_macros::{pin_init, pin_scope};
+/// struct Node<'a> {
+///     value: u32,
+///     this: Option<&'a Node<'a>>,
+/// }
+/// impl<'a> Node<'a> {
+///     pin_init!(fn init<'a>(this, value: u32) {
+///         this.value = value;
+///         this.this = None;
+///     });
+/// }
+/// impl Node<'_> {
+///     // `pub fn build<R>(value: u32, f: impl for<'s> FnOnce(Pin<&'s mut Node<'s>>) -> R) -> R`
+///     pin_scope!(pub fn build<'s>(value: u32) -> Node<'s> = init(value));
+/// }
+///
+/// let doubled = Node::build(2, |node| node.value * 2);
+/// assert_eq!(doubled, 4);
+/// ```
+#[macro_export]
+macro_rules! pin_scope {
+    ($(#[$attr:meta])* $v:vis fn $name:ident<$a:lifetime>($($argn:ident: $argt:ty),* $(,)?) -> $vart:ty = $methodn:ident($($arg:expr),* $(,)?)) => {
+        $(#[$attr])*
+        $v fn $name<R>($($argn: $argt,)* f: impl for<$a> FnOnce(core::pin::Pin<&$a mut $vart>) -> R) -> R {
+            fn __scope<$a, R>(
+                __uninit_ptr: core::pin::Pin<&$a mut core::mem::MaybeUninit<$vart>>,
+                $($argn: $argt,)*
+                f: impl FnOnce(core::pin::Pin<&$a mut $vart>) -> R,
+            ) -> R {
+                f(<$vart>::$methodn(__uninit_ptr, $($arg),*))
+            }
+
+            $crate::__pin_storage!([] __uninit_ptr: core::mem::MaybeUninit<_>);
+            __scope(__uninit_ptr, $($argn,)* f)
+        }
+    };
+}
+/// Initializes owned immovable value on stack with its [`PinDefault`] value.
+#[macro_export]
+macro_rules! pin_new_default {
+    ($varn:ident: $vart:ty) => {
+        $crate::uninit_pin!(__uninit_ptr: $vart);
+        let $varn = <$vart as $crate::PinDefault>::pin_default(__uninit_ptr);
+    };
+    (mut $varn:ident: $vart:ty) => {
+        $crate::uninit_pin!(__uninit_ptr: $vart);
+        let mut $varn = <$vart as $crate::PinDefault>::pin_default(__uninit_ptr);
+    };
+}
+/// Resets a pinned value to its [`PinDefault`] value in place, e.g. to reuse
+/// a pooled value, evaluating to the new `Pin<&mut T>`. The value is kept in
+/// a `Pin<&mut PinOption<T>>` slot: the old value, if any, is dropped first,
+/// and the self-references are rebuilt by `pin_default`, while the storage
+/// stays the same. The slot is usually reborrowed by `as_mut()`, if
+/// `PinDefault` is implemented for any lifetime.
+///
+/// The slot is left `None` if the drop or `pin_default` panics, so it never
+/// holds a dropped value, and may be reset again (a plain `Pin<&mut T>`
+/// can't be reset, since its owner would drop the value again). A
+/// [`PinOption`] field is reset by `pin_field_reinit!` in the same manner.
+/// ```
+/// # use std::{cell::Cell, marker::PhantomPinned, pin::{pin, Pin}};
+/// # use pin_macros::{pin_init, pin_reset, PinDefault, PinOption};
+/// thread_local!(static DROPS: Cell<u32> = Cell::new(0));
+///
+/// struct List {
+///     nodes: [u32; 4],
+///     tail: *mut u32,
+///     marker: PhantomPinned,
+/// }
+/// impl<'a> PinDefault<'a> for List {
+///     pin_init!(fn pin_default<'a>(this) {
+///         this.nodes = [0; 4];
+///         this.tail = this.nodes.as_mut_ptr();
+///         this.marker = PhantomPinned;
+///     });
+/// }
+/// impl List {
+///     fn push(self: Pin<&mut Self>, value: u32) {
+///         let this = unsafe { self.get_unchecked_mut() };
+///         unsafe {
+///             this.tail.write(value);
+///             this.tail = this.tail.add(1);
+///         }
+///     }
+///     fn len(&self) -> usize {
+///         (self.tail as usize - self.nodes.as_ptr() as usize) / 4
+///     }
+/// }
+/// impl Drop for List {
+///     fn drop(&mut self) {
+///         DROPS.with(|drops| drops.set(drops.get() + 1));
+///     }
+/// }
+///
+/// let mut slot = pin!(PinOption::<List>::none());
+/// let mut list = pin_reset!(slot.as_mut());
+/// list.as_mut().push(1);
+/// list.as_mut().push(2);
+/// assert_eq!(list.len(), 2);
+///
+/// let mut list = pin_reset!(slot.as_mut());
+/// assert_eq!((list.len(), DROPS.with(Cell::get)), (0, 1));
+/// list.as_mut().push(3);
+/// assert_eq!((list.len(), list.nodes[0]), (1, 3));
+/// assert!(std::ptr::eq(list.tail, &list.nodes[1]));
+/// ```
+///
+/// A panicking `pin_default` leaves the slot empty:
+/// ```
+/// # use std::{cell::Cell, marker::PhantomPinned, panic, pin::pin};
+/// # use pin_macros::{pin_init, pin_reset, PinDefault, PinOption};
+/// thread_local!(static FAIL: Cell<bool> = Cell::new(false));
+///
+/// struct Conn {
+///     id: u32,
+///     marker: PhantomPinned,
+/// }
+/// impl<'a> PinDefault<'a> for Conn {
+///     pin_init!(fn pin_default<'a>(this) {
+///         assert!(!FAIL.with(Cell::get), "connection refused");
+///         this.id = 1;
+///         this.marker = PhantomPinned;
+///     });
+/// }
+///
+/// let mut slot = pin!(PinOption::<Conn>::none());
+/// assert_eq!(pin_reset!(slot.as_mut()).id, 1);
+///
+/// FAIL.with(|fail| fail.set(true));
+/// let result = panic::catch_unwind(panic::AssertUnwindSafe(|| {
+///     pin_reset!(slot.as_mut());
+/// }));
+/// assert!(result.is_err() && slot.is_none());
+///
+/// FAIL.with(|fail| fail.set(false));
+/// assert_eq!(pin_reset!(slot.as_mut()).id, 1);
+/// assert!(slot.is_some());
+/// ```
+#[macro_export]
+macro_rules! pin_reset {
+    ($ptr:expr) => {
+        $crate::__pin_reset($ptr)
+    };
+}
+/// Initializes owned immovable value on stack with zeros. The type must
+/// implement [`PinZeroable`].
+#[macro_export]
+macro_rules! pin_new_zeroed {
+    ($varn:ident: $vart:ty) => {
+        $crate::uninit_pin!(__uninit_ptr: $vart);
+        let $varn = $crate::init_zeroed(__uninit_ptr);
+    };
+    (mut $varn:ident: $vart:ty) => {
+        $crate::uninit_pin!(__uninit_ptr: $vart);
+        let mut $varn = $crate::init_zeroed(__uninit_ptr);
+    };
+}
+/// Initializes owned immovable value on stack with zeros like
+/// `pin_new_zeroed!`, then passes `Pin<&mut T>` to the body under the given
+/// name, e.g. to set some fields through getters or `pin_field_init!`, while
+/// the other bytes (reserved fields, padding) are guaranteed to stay zero. The
+/// type must implement [`PinZeroable`]. The body is inlined, so `?` and
+/// `return` apply to the enclosing function.
+/// ```
+/// # use std::{marker::PhantomPinned, mem::size_of};
+/// # use pin_macros::{field_unpin, pin_new_zeroed_then, PinZeroable};
+/// #[repr(C)]
+/// struct Request {
+///     kind: u8,
+///     // 3 bytes of padding
+///     len: u32,
+///     reserved: [u32; 2],
+///     marker: PhantomPinned,
+/// }
+/// unsafe impl PinZeroable for Request {}
+/// impl Request {
+///     field_unpin!(kind: u8);
+///     field_unpin!(len: u32);
+/// }
+///
+/// pin_new_zeroed_then!(request: Request = |this| {
+///     *this.as_mut().kind() = 1;
+///     *this.as_mut().len() = 8;
+/// });
+/// assert_eq!((request.kind, request.len, request.reserved), (1, 8, [0; 2]));
+/// let bytes = unsafe { std::slice::from_raw_parts(&*request as *const Request as *const u8, size_of::<Request>()) };
+/// assert_eq!(bytes[1..4], [0; 3]);
+/// ```
+#[macro_export]
+macro_rules! pin_new_zeroed_then {
+    ($varn:ident: $vart:ty = |$this:ident| $body:expr) => {
+        $crate::pin_new_zeroed!(mut __zeroed_ptr: $vart);
+        $crate::pin_new_zeroed_then!(@body __zeroed_ptr, $this, $body);
+        let $varn = __zeroed_ptr;
+    };
+    (mut $varn:ident: $vart:ty = |$this:ident| $body:expr) => {
+        $crate::pin_new_zeroed!(mut __zeroed_ptr: $vart);
+        $crate::pin_new_zeroed_then!(@body __zeroed_ptr, $this, $body);
+        let mut $varn = __zeroed_ptr;
+    };
+    (@body $ptr:ident, $this:ident, $body:expr) => {{
+        #[allow(unused_mut)]
+        let mut $this = $ptr.as_mut();
+        $body;
+    }};
+}
+/// Initializes owned immovable value on stack from the leading bytes of a
+/// slice, storing `Result<Pin<&mut T>, BytesLenError>`, which is `Err` if the
+/// slice is shorter than `T`. The type must implement
+/// `bytemuck::AnyBitPattern`.
+/// ```
+/// # use std::marker::PhantomPinned;
+/// # use bytemuck::{AnyBitPattern, Zeroable};
+/// # use pin_macros::pin_new_from_bytes;
+/// #[repr(C)]
+/// #[derive(Clone, Copy)]
+/// struct Header {
+///     len: [u8; 2],
+///     kind: u8,
+///     marker: PhantomPinned,
+/// }
+/// unsafe impl Zeroable for Header {}
+/// unsafe impl AnyBitPattern for Header {}
+///
+/// let frame = [0, 5, 1, 0xff, 0xff];
+/// pin_new_from_bytes!(header: Header = &frame);
+/// let header = header.unwrap();
+/// assert_eq!((u16::from_be_bytes(header.len), header.kind), (5, 1));
+/// pin_new_from_bytes!(short: Header = &frame[..2]);
+/// assert!(matches!(short, Err(err) if err.actual == 2));
+/// ```
+#[cfg(feature = "bytemuck")]
+#[macro_export]
+macro_rules! pin_new_from_bytes {
+    ($varn:ident: $vart:ty = $bytes:expr) => {
+        $crate::uninit_pin!(__uninit_ptr: $vart);
+        let $varn = $crate::init_from_bytes::<$vart>(__uninit_ptr, $bytes);
+    };
+    (mut $varn:ident: $vart:ty = $bytes:expr) => {
+        $crate::uninit_pin!(__uninit_ptr: $vart);
+        let mut $varn = $crate::init_from_bytes::<$vart>(__uninit_ptr, $bytes);
+    };
+}
+/// Implements [`PinDeserialize`] for a struct with named fields, which are
+/// all listed in declaration order. A plain field is deserialized as
+/// usual and written in place, a field marked with `#[pin]` is deserialized
+/// in place through its own `PinDeserialize`, and a field followed by `=` is
+/// not deserialized, but initialized with the value after it, e.g. a
+/// self-reference to be set up later. Lifetimes of `Self` are declared after
+/// `impl` like in `pin_init!`. Unknown fields are ignored.
+///
+/// If deserialization fails, e.g. a field is missing or duplicated, the
+/// already written fields are dropped.
+/// ```
+/// # use std::marker::PhantomPinned;
+/// # use pin_macros::{pin_deserialize, pin_new_deserialize};
+/// struct Limits {
+///     max: u32,
+///     marker: PhantomPinned,
+/// }
+/// pin_deserialize!(impl Limits {
+///     max: u32,
+///     marker: PhantomPinned = PhantomPinned,
+/// });
+///
+/// struct Config<'a> {
+///     name: String,
+///     limits: Limits,
+///     active: Option<&'a Limits>,
+/// }
+/// pin_deserialize!(impl<'a> Config<'a> {
+///     name: String,
+///     #[pin] limits: Limits,
+///     active: Option<&'a Limits> = None,
+/// });
+///
+/// let mut json = serde_json::Deserializer::from_str(r#"{"name": "main", "limits": {"max": 3}}"#);
+/// pin_new_deserialize!(config: Config = &mut json);
+/// let config = config.unwrap();
+/// assert_eq!((config.name.as_str(), config.limits.max), ("main", 3));
+///
+/// let mut json = serde_json::Deserializer::from_str(r#"{"name": "main", "limits": {}}"#);
+/// pin_new_deserialize!(config: Config = &mut json);
+/// assert!(config.is_err());
+/// ```
+#[cfg(feature = "serde")]
+#[macro_export]
+macro_rules! pin_deserialize {
+    ($(#[$implattr:meta])* impl<$($gen:lifetime),* $(,)?> $name:ident $(<$($arg:lifetime),* $(,)?>)? { $($fields:tt)* }) => {
+        $crate::pin_deserialize!(@parse [$(#[$implattr])*] [$($gen),*] $name [$($($arg),*)?] [] [] $($fields)*);
+    };
+    ($(#[$implattr:meta])* impl $name:ident { $($fields:tt)* }) => {
+        $crate::pin_deserialize!(@parse [$(#[$implattr])*] [] $name [] [] [] $($fields)*);
+    };
+    (@parse $attrs:tt $gen:tt $name:ident $args:tt [$($names:tt)*] [$($entries:tt)*]
+        #[pin] $fieldn:ident: $fieldt:ty $(, $($rest:tt)*)?) => {
+        $crate::pin_deserialize!(@parse $attrs $gen $name $args [$($names)* $fieldn] [$($entries)* [pin $fieldn: $fieldt]] $($($rest)*)?);
+    };
+    (@parse $attrs:tt $gen:tt $name:ident $args:tt [$($names:tt)*] [$($entries:tt)*]
+        $fieldn:ident: $fieldt:ty $(, $($rest:tt)*)?) => {
+        $crate::pin_deserialize!(@parse $attrs $gen $name $args [$($names)* $fieldn] [$($entries)* [plain $fieldn: $fieldt]] $($($rest)*)?);
+    };
+    (@parse $attrs:tt $gen:tt $name:ident $args:tt [$($names:tt)*] [$($entries:tt)*]
+        $fieldn:ident: $fieldt:ty = $default:expr $(, $($rest:tt)*)?) => {
+        $crate::pin_deserialize!(@parse $attrs $gen $name $args [$($names)*] [$($entries)* [default $fieldn: $fieldt = $default]] $($($rest)*)?);
+    };
+    (@parse [$($attr:tt)*] [$($gen:lifetime),*] $name:ident [$($arg:lifetime),*] [$($names:ident)*]
+        [$([$kind:ident $fieldn:ident: $fieldt:ty $(= $default:expr)?])*]) => {
+        $($attr)*
+        impl<'de, $($gen),*> $crate::__serde::PinVisit<'de> for $name<$($arg),*> {
+            const NAME: &'static str = stringify!($name);
+            const FIELDS: &'static [&'static str] = &[$(stringify!($names)),*];
+
+            fn visit_map<A: $crate::__serde::MapAccess<'de>>(
+                mut __uninit_ptr: core::pin::Pin<&mut core::mem::MaybeUninit<Self>>,
+                mut __map: A,
+            ) -> core::result::Result<(), A::Error> {
+                let __ptr = unsafe { __uninit_ptr.as_mut().get_unchecked_mut().as_mut_ptr() };
+                let mut __guard = unsafe {
+                    $crate::InitGuard::<Self, { 0 $(+ $crate::pin_deserialize!(@one $fieldn))* }>::with_capacity(__ptr)
+                };
+                {
+                    $($crate::pin_deserialize!(@seen [$kind] $fieldn);)*
+                    let __key_seed = $crate::__serde::Key(<Self as $crate::__serde::PinVisit<'de>>::FIELDS);
+                    while let Some(__key) = __map.next_key_seed(__key_seed)? {
+                        $($crate::pin_deserialize!(@map [$kind] $fieldn: $fieldt, __key, __map, __ptr, __guard);)*
+                        __map.next_value::<$crate::__serde::IgnoredAny>()?;
+                    }
+                    $($crate::pin_deserialize!(@missing [$kind] $fieldn);)*
+                }
+                $($crate::pin_deserialize!(@default [$kind] $fieldn $(= $default)?, __ptr, __guard);)*
+                __guard.disarm();
+                Ok(())
+            }
+            fn visit_seq<A: $crate::__serde::SeqAccess<'de>>(
+                mut __uninit_ptr: core::pin::Pin<&mut core::mem::MaybeUninit<Self>>,
+                mut __seq: A,
+            ) -> core::result::Result<(), A::Error> {
+                let __ptr = unsafe { __uninit_ptr.as_mut().get_unchecked_mut().as_mut_ptr() };
+                let mut __guard = unsafe {
+                    $crate::InitGuard::<Self, { 0 $(+ $crate::pin_deserialize!(@one $fieldn))* }>::with_capacity(__ptr)
+                };
+                let mut __len = 0;
+                $($crate::pin_deserialize!(@seq [$kind] $name $fieldn: $fieldt, __seq, __ptr, __guard, __len);)*
+                $($crate::pin_deserialize!(@default [$kind] $fieldn $(= $default)?, __ptr, __guard);)*
+                __guard.disarm();
+                Ok(())
+            }
+        }
+        $($attr)*
+        impl<'de, $($gen),*> $crate::PinDeserialize<'de> for $name<$($arg),*> {
+            fn deserialize_pin<'__a, D: $crate::__serde::Deserializer<'de>>(
+                ptr: core::pin::Pin<&'__a mut core::mem::MaybeUninit<Self>>,
+                deserializer: D,
+            ) -> core::result::Result<core::pin::Pin<&'__a mut Self>, D::Error> {
+                $crate::__serde::deserialize_struct(ptr, deserializer)
+            }
+        }
+    };
+    (@seen [default] $fieldn:ident) => {};
+    (@seen [$kind:ident] $fieldn:ident) => {
+        let mut $fieldn = false;
+    };
+    (@map [default] $($_:tt)*) => {};
+    (@map [$kind:ident] $fieldn:ident: $fieldt:ty, $key:ident, $map:ident, $ptr:ident, $guard:ident) => {
+        if $key == Some(stringify!($fieldn)) {
+            if $fieldn {
+                return Err($crate::__serde::Error::duplicate_field(stringify!($fieldn)));
+            }
+            $crate::pin_deserialize!(@value [$kind] $fieldn: $fieldt, $map, $ptr);
+            $crate::pin_deserialize!(@track $fieldn, $ptr, $guard);
+            $fieldn = true;
+            continue;
+        }
+    };
+    (@value [plain] $fieldn:ident: $fieldt:ty, $map:ident, $ptr:ident) => {
+        let __value = $map.next_value::<$fieldt>()?;
+        unsafe { core::ptr::write(core::ptr::addr_of_mut!((*$ptr).$fieldn), __value) };
+    };
+    (@value [pin] $fieldn:ident: $fieldt:ty, $map:ident, $ptr:ident) => {
+        $map.next_value_seed($crate::pin_deserialize!(@in_place $fieldn: $fieldt, $ptr))?;
+    };
+    (@seq [default] $($_:tt)*) => {};
+    (@seq [$kind:ident] $name:ident $fieldn:ident: $fieldt:ty, $seq:ident, $ptr:ident, $guard:ident, $len:ident) => {
+        if !$crate::pin_deserialize!(@element [$kind] $fieldn: $fieldt, $seq, $ptr) {
+            return Err($crate::__serde::Error::invalid_length($len, &concat!("struct ", stringify!($name))));
+        }
+        $crate::pin_deserialize!(@track $fieldn, $ptr, $guard);
+        $len += 1;
+    };
+    // Evaluates to whether the sequence had the element.
+    (@element [plain] $fieldn:ident: $fieldt:ty, $seq:ident, $ptr:ident) => {
+        match $seq.next_element::<$fieldt>()? {
+            Some(__value) => {
+                unsafe { core::ptr::write(core::ptr::addr_of_mut!((*$ptr).$fieldn), __value) };
+                true
+            }
+            None => false,
+        }
+    };
+    (@element [pin] $fieldn:ident: $fieldt:ty, $seq:ident, $ptr:ident) => {
+        $seq.next_element_seed($crate::pin_deserialize!(@in_place $fieldn: $fieldt, $ptr))?.is_some()
+    };
+    (@in_place $fieldn:ident: $fieldt:ty, $ptr:ident) => {
+        $crate::__serde::InPlace::<$fieldt>(unsafe {
+            core::pin::Pin::new_unchecked(&mut *(core::ptr::addr_of_mut!((*$ptr).$fieldn) as *mut core::mem::MaybeUninit<$fieldt>))
+        })
+    };
+    (@one $fieldn:ident) => {
+        1
+    };
+    (@missing [default] $fieldn:ident) => {};
+    (@missing [$kind:ident] $fieldn:ident) => {
+        if !$fieldn {
+            return Err($crate::__serde::Error::missing_field(stringify!($fieldn)));
+        }
+    };
+    (@default [default] $fieldn:ident = $default:expr, $ptr:ident, $guard:ident) => {
+        unsafe { core::ptr::write(core::ptr::addr_of_mut!((*$ptr).$fieldn), $default) };
+        $crate::pin_deserialize!(@track $fieldn, $ptr, $guard);
+    };
+    (@default [$kind:ident] $fieldn:ident, $ptr:ident, $guard:ident) => {};
+    (@track $fieldn:ident, $ptr:ident, $guard:ident) => {
+        unsafe {
+            $guard.mark(core::ptr::addr_of_mut!((*$ptr).$fieldn));
+            $guard.push(|__ptr| core::ptr::drop_in_place(core::ptr::addr_of_mut!((*__ptr).$fieldn)));
+        }
+    };
+}
+/// Deserializes owned immovable value right into storage on stack, storing
+/// `Result<Pin<&mut T>, D::Error>`. The type must implement
+/// [`PinDeserialize`], e.g. through `pin_deserialize!`.
+#[cfg(feature = "serde")]
+#[macro_export]
+macro_rules! pin_new_deserialize {
+    ($varn:ident: $vart:ty = $deserializer:expr) => {
+        $crate::uninit_pin!(__uninit_ptr: $vart);
+        let $varn = <$vart as $crate::PinDeserialize>::deserialize_pin(__uninit_ptr, $deserializer);
+    };
+    (mut $varn:ident: $vart:ty = $deserializer:expr) => {
+        $crate::uninit_pin!(__uninit_ptr: $vart);
+        let mut $varn = <$vart as $crate::PinDeserialize>::deserialize_pin(__uninit_ptr, $deserializer);
+    };
+}
+/// Initializes owned immovable value in storage allocated by a [`PinAlloc`],
+/// storing `Option<Pin<&mut T>>` borrowing the allocator, which is `None` if
+/// the allocator is out of memory. Like `pin_new!` storage, the storage is
+/// not dropped as `T`.
+/// ```
+/// # use std::{cell::{Cell, UnsafeCell}, marker::PhantomPinned, mem::{self, MaybeUninit}, pin::Pin, ptr};
+/// # use pin_macros::{pin_new_in, PinAlloc};
+/// struct Bump {
+///     buf: UnsafeCell<[MaybeUninit<u64>; 2]>,
+///     used: Cell<usize>,
+/// }
+/// unsafe impl PinAlloc for Bump {
+///     fn alloc_uninit<T>(&self) -> *mut MaybeUninit<T> {
+///         let base = self.buf.get() as usize;
+///         let start = (base + self.used.get()).next_multiple_of(mem::align_of::<T>()) - base;
+///         let end = start + mem::size_of::<T>();
+///         if end > mem::size_of_val(&self.buf) {
+///             return ptr::null_mut();
+///         }
+///         self.used.set(end);
+///         (base + start) as *mut MaybeUninit<T>
+///     }
+/// }
+///
+/// struct Node {
+///     id: u64,
+///     marker: PhantomPinned,
+/// }
+/// impl Node {
+///     fn init(ptr: Pin<&mut MaybeUninit<Self>>, id: u64) -> Pin<&mut Self> {
+///         let ptr = unsafe { ptr.get_unchecked_mut() };
+///         unsafe { Pin::new_unchecked(ptr.write(Self { id, marker: PhantomPinned })) }
+///     }
+/// }
+///
+/// let arena = Bump { buf: UnsafeCell::new([MaybeUninit::uninit(); 2]), used: Cell::new(0) };
+/// pin_new_in!(a: Node = init(1), arena);
+/// pin_new_in!(b: Node = init(2), &arena);
+/// assert_eq!(a.unwrap().id, 1);
+/// assert_eq!(b.unwrap().id, 2);
+/// pin_new_in!(c: Node = init(3), arena);
+/// assert!(c.is_none());
+/// ```
+#[macro_export]
+macro_rules! pin_new_in {
+    ($varn:ident: $vart:ty = $methodn:ident($($arg:expr),* $(,)?), $alloc:expr) => {
+        let $varn = $crate::pin_new_in!(@expand $vart, $methodn($($arg),*), $alloc);
+    };
+    (mut $varn:ident: $vart:ty = $methodn:ident($($arg:expr),* $(,)?), $alloc:expr) => {
+        let mut $varn = $crate::pin_new_in!(@expand $vart, $methodn($($arg),*), $alloc);
+    };
+    (@expand $vart:ty, $methodn:ident($($arg:expr),*), $alloc:expr) => {
+        match {
+            use $crate::__PinAllocExt as _;
+            ($alloc).__pin_uninit::<$vart>()
+        } {
+            Some(__uninit_ptr) => Some(<$vart>::$methodn(__uninit_ptr, $($arg),*)),
+            None => None,
+        }
+    };
+}
+/// Moves an already constructed value into storage on stack, storing
+/// `Pin<&mut T>`. Any pointers the value held into itself are dangling after
+/// the move, so the self-references must be established again, e.g. by
+/// `pin_field_init!`, before they are used.
+/// ```
+/// # use std::pin::Pin;
+/// # use pin_macros::{pin_field_init, pin_init_from};
+/// struct Cursor<'a> {
+///     data: [u8; 4],
+///     first: Option<&'a mut u8>,
+/// }
+/// impl<'a> Cursor<'a> {
+///     fn link(mut self: Pin<&'a mut Self>) {
+///         pin_field_init!(self: |data => first| &mut data[0]);
+///     }
+/// }
+///
+/// let cursor = Cursor { data: [1, 2, 3, 4], first: None };
+/// pin_init_from!(mut pinned: Cursor = cursor);
+/// pinned.as_mut().link();
+/// ```
+#[macro_export]
+macro_rules! pin_init_from {
+    ($varn:ident: $vart:ty = $value:expr) => {
+        $crate::uninit_pin!(__uninit_ptr: $vart);
+        let $varn = $crate::init_from::<$vart>(__uninit_ptr, $value);
+    };
+    (mut $varn:ident: $vart:ty = $value:expr) => {
+        $crate::uninit_pin!(__uninit_ptr: $vart);
+        let mut $varn = $crate::init_from::<$vart>(__uninit_ptr, $value);
+    };
+}
+/// Initializes owned immovable value on heap, storing `Pin<Box<T>>`.
+/// Panics if the initializer returns a pointer to another value than the
+/// box, which is not initialized then.
+/// ```should_panic
+/// # use std::{mem::MaybeUninit, pin::Pin};
+/// # use pin_macros::pin_box_new;
+/// struct Counter(u32);
+/// impl Counter {
+///     fn init(_ptr: Pin<&mut MaybeUninit<Self>>) -> Pin<&mut Self> {
+///         Pin::new(Box::leak(Box::new(Counter(0))))
+///     }
+/// }
+///
+/// pin_box_new!(counter: Counter = init());
+/// ```
+#[cfg(feature = "alloc")]
+#[macro_export]
+macro_rules! pin_box_new {
+    ($varn:ident: $vart:ty = $methodn:ident($($arg:expr),* $(,)?)) => {
+        let $varn = $crate::pin_box_new!(@expand $vart, $methodn($($arg),*));
+    };
+    (mut $varn:ident: $vart:ty = $methodn:ident($($arg:expr),* $(,)?)) => {
+        let mut $varn = $crate::pin_box_new!(@expand $vart, $methodn($($arg),*));
+    };
+    (@expand $vart:ty, $methodn:ident($($arg:expr),*)) => {{
+        let __uninit = $crate::UninitBox::<$vart>::new();
+        $crate::__assert_init(<$vart>::$methodn(unsafe { __uninit.as_uninit_pin() }, $($arg),*), __uninit.as_ptr());
+        unsafe { __uninit.assume_init() }
+    }};
+}
+/// Reference counted pointers for the macros, which are available without
+/// `std`.
+#[cfg(feature = "alloc")]
+#[doc(hidden)]
+pub mod __alloc {
+    pub use alloc::{boxed::Box, rc::Rc};
+    #[cfg(target_has_atomic = "ptr")]
+    pub use alloc::sync::Arc;
+}
+/// Initializes owned immovable value inside a new `Rc`, storing `Pin<Rc<T>>`.
+#[cfg(feature = "alloc")]
+#[macro_export]
+macro_rules! pin_rc_new {
+    ($varn:ident: $vart:ty = $methodn:ident($($arg:expr),* $(,)?)) => {
+        let $varn = $crate::pin_rc_new!(@expand Rc, $vart, $methodn($($arg),*));
+    };
+    (mut $varn:ident: $vart:ty = $methodn:ident($($arg:expr),* $(,)?)) => {
+        let mut $varn = $crate::pin_rc_new!(@expand Rc, $vart, $methodn($($arg),*));
+    };
+    (@expand $rc:ident, $vart:ty, $methodn:ident($($arg:expr),*)) => {{
+        let mut __uninit = $crate::__alloc::$rc::<$vart>::new_uninit();
+        let __uninit_ptr = match $crate::__alloc::$rc::get_mut(&mut __uninit) {
+            Some(__uninit) => __uninit as *mut core::mem::MaybeUninit<$vart>,
+            None => unreachable!(),
+        };
+        $crate::__assert_init(
+            <$vart>::$methodn(unsafe { core::pin::Pin::new_unchecked(&mut *__uninit_ptr) }, $($arg),*),
+            __uninit_ptr as *const $vart,
+        );
+        unsafe { core::pin::Pin::new_unchecked(__uninit.assume_init()) }
+    }};
+}
+/// Initializes owned immovable value inside a new `Arc`, storing
+/// `Pin<Arc<T>>`.
+#[cfg(all(feature = "alloc", target_has_atomic = "ptr"))]
+#[macro_export]
+macro_rules! pin_arc_new {
+    ($varn:ident: $vart:ty = $methodn:ident($($arg:expr),* $(,)?)) => {
+        let $varn = $crate::pin_rc_new!(@expand Arc, $vart, $methodn($($arg),*));
+    };
+    (mut $varn:ident: $vart:ty = $methodn:ident($($arg:expr),* $(,)?)) => {
+        let mut $varn = $crate::pin_rc_new!(@expand Arc, $vart, $methodn($($arg),*));
+    };
+}
+/// Defines a [`PinThreadLocal`] static, whose value is initialized on the
+/// first access of each thread through the initializer, e.g.
+/// `pin_thread_local!(static SCRATCH: Scratch = init(64));`. The arguments
+/// are evaluated on each initialization and can't refer to local variables.
+/// ```
+/// # use std::{cell::Cell, marker::PhantomPinned, ptr};
+/// # use pin_macros::{pin_init, pin_thread_local};
+/// struct Scratch {
+///     uses: Cell<u32>,
+///     this: *const Scratch,
+///     marker: PhantomPinned,
+/// }
+/// impl<'a> Scratch {
+///     pin_init!(fn init<'a>(this, uses: u32) {
+///         this.uses = Cell::new(uses);
+///         this.this = &*pin_init_clone!();
+///         this.marker = PhantomPinned;
+///     });
+/// }
+/// pin_thread_local!(static SCRATCH: Scratch = init(0));
+///
+/// let bump = || SCRATCH.with_ref(|scratch| {
+///     assert!(ptr::eq(scratch.this, &*scratch));
+///     scratch.uses.set(scratch.uses.get() + 1);
+///     scratch.uses.get()
+/// });
+/// assert_eq!((bump(), bump()), (1, 2));
+/// std::thread::spawn(move || assert_eq!(bump(), 1)).join().unwrap();
+/// assert_eq!(bump(), 3);
+/// ```
+#[cfg(feature = "std")]
+#[macro_export]
+macro_rules! pin_thread_local {
+    ($(#[$attr:meta])* $v:vis static $name:ident: $vart:ty = $methodn:ident($($arg:expr),* $(,)?) $(;)?) => {
+        $(#[$attr])*
+        $v static $name: $crate::PinThreadLocal<$vart> = {
+            std::thread_local!(static __STORAGE: $crate::__PinLocalCell<$vart> = const { $crate::__PinLocalCell::uninit() });
+            fn __init(__uninit_ptr: core::pin::Pin<&mut core::mem::MaybeUninit<$vart>>) -> core::pin::Pin<&mut $vart> {
+                <$vart>::$methodn(__uninit_ptr, $($arg),*)
+            }
+            $crate::PinThreadLocal::__new(&__STORAGE, __init)
+        };
+    };
+}
+/// Initializes owned array of immovable values on stack, calling the
+/// initializer for each element.
+///
+/// The length may be a const generic parameter of the surrounding item,
+/// e.g. in reusable container code. If an initializer panics, the elements
+/// initialized before it are dropped, however many there are for the
+/// instantiated length.
+/// ```
+/// # use std::{cell::Cell, marker::PhantomPinned, mem::MaybeUninit, panic, pin::Pin};
+/// # use pin_macros::pin_array_new;
+/// struct Slot<'a> {
+///     live: &'a Cell<usize>,
+///     marker: PhantomPinned,
+/// }
+/// impl<'a> Slot<'a> {
+///     fn init<'b>(ptr: Pin<&'b mut MaybeUninit<Self>>, live: &'a Cell<usize>, fail_at: usize) -> Pin<&'b mut Self> {
+///         assert_ne!(live.get(), fail_at);
+///         live.set(live.get() + 1);
+///         unsafe { Pin::new_unchecked(ptr.get_unchecked_mut().write(Self { live, marker: PhantomPinned })) }
+///     }
+/// }
+/// impl Drop for Slot<'_> {
+///     fn drop(&mut self) {
+///         self.live.set(self.live.get() - 1);
+///     }
+/// }
+///
+/// fn build<const N: usize>(live: &Cell<usize>, fail_at: usize) -> usize {
+///     pin_array_new!(slots: [Slot; N] = init(live, fail_at));
+///     let len = slots.len();
+///     // Like `pin_new!`, the array itself is never dropped.
+///     unsafe { std::ptr::drop_in_place(slots.get_unchecked_mut()) };
+///     len
+/// }
+///
+/// let live = Cell::new(0);
+/// assert_eq!(build::<2>(&live, usize::MAX), 2);
+/// assert_eq!(build::<5>(&live, usize::MAX), 5);
+/// assert!(panic::catch_unwind(panic::AssertUnwindSafe(|| build::<5>(&live, 3))).is_err());
+/// assert_eq!(live.get(), 0);
+/// ```
+#[macro_export]
+macro_rules! pin_array_new {
+    ($varn:ident: [$vart:ty; $len:expr] = $methodn:ident($($arg:expr),* $(,)?)) => {
+        $crate::uninit_pin!(__uninit_ptr: [$vart; $len]);
+        let $varn = $crate::pin_array_new!(@init __uninit_ptr, $vart, $methodn($($arg),*));
+    };
+    (mut $varn:ident: [$vart:ty; $len:expr] = $methodn:ident($($arg:expr),* $(,)?)) => {
+        $crate::uninit_pin!(__uninit_ptr: [$vart; $len]);
+        let mut $varn = $crate::pin_array_new!(@init __uninit_ptr, $vart, $methodn($($arg),*));
+    };
+    (@init $uninit_ptr:ident, $vart:ty, $methodn:ident($($arg:expr),*)) => {{
+        // Defined outside of the `unsafe` block, which must not cover the
+        // arguments.
+        let __init = |_, __elem_ptr| <$vart>::$methodn(__elem_ptr, $($arg),*);
+        // The storage is only passed on to the initializer, which returns it.
+        unsafe { $crate::__init_array($uninit_ptr, __init) }
+    }};
+}
+/// Initializes owned array of immovable values on stack like `pin_array_new!`,
+/// but the identifier between pipes binds the element index (`usize`), from
+/// which the initializer arguments are computed, e.g. `pin_array_new_with!(
+/// table: [Entry; 16] = |i| init(hash_of(i)))`. If an argument or an
+/// initializer panics, the already initialized elements are dropped, and an
+/// initializer returning another pin than the one of its element panics.
+/// ```
+/// # use std::{marker::PhantomPinned, mem::MaybeUninit, pin::Pin};
+/// # use pin_macros::pin_array_new_with;
+/// struct Entry {
+///     square: usize,
+///     marker: PhantomPinned,
+/// }
+/// impl Entry {
+///     fn init(ptr: Pin<&mut MaybeUninit<Self>>, square: usize) -> Pin<&mut Self> {
+///         unsafe { Pin::new_unchecked(ptr.get_unchecked_mut().write(Self { square, marker: PhantomPinned })) }
+///     }
+/// }
+///
+/// pin_array_new_with!(table: [Entry; 4] = |i| init(i * i));
+/// assert_eq!(table.iter().map(|entry| entry.square).collect::<Vec<_>>(), [0, 1, 4, 9]);
+/// ```
+#[macro_export]
+macro_rules! pin_array_new_with {
+    ($varn:ident: [$vart:ty; $len:expr] = |$index:ident| $methodn:ident($($arg:expr),* $(,)?)) => {
+        $crate::uninit_pin!(__uninit_ptr: [$vart; $len]);
+        let $varn = $crate::pin_array_new_with!(@init __uninit_ptr, $vart, |$index| $methodn($($arg),*));
+    };
+    (mut $varn:ident: [$vart:ty; $len:expr] = |$index:ident| $methodn:ident($($arg:expr),* $(,)?)) => {
+        $crate::uninit_pin!(__uninit_ptr: [$vart; $len]);
+        let mut $varn = $crate::pin_array_new_with!(@init __uninit_ptr, $vart, |$index| $methodn($($arg),*));
+    };
+    (@init $uninit_ptr:ident, $vart:ty, |$index:ident| $methodn:ident($($arg:expr),*)) => {{
+        let __init = |__index, __elem_ptr| {
+            let $index: usize = __index;
+            <$vart>::$methodn(__elem_ptr, $($arg),*)
+        };
+        // The storage is only passed on to the initializer, which returns it.
+        unsafe { $crate::__init_array($uninit_ptr, __init) }
+    }};
+}
+/// Initializes owned slice of immovable values of runtime length on heap,
+/// calling the initializer for each element and storing `Pin<Box<[T]>>`. An
+/// identifier between pipes before the initializer binds the element index.
+/// An initializer returning another pin than the one of its element panics.
+/// ```
+/// # use std::{marker::PhantomPinned, mem::MaybeUninit, pin::Pin};
+/// # use pin_macros::pin_boxed_slice_new;
+/// struct Node {
+///     index: usize,
+///     marker: PhantomPinned,
+/// }
+/// impl Node {
+///     fn init(ptr: Pin<&mut MaybeUninit<Self>>, index: usize) -> Pin<&mut Self> {
+///         let ptr = unsafe { ptr.get_unchecked_mut() };
+///         unsafe { Pin::new_unchecked(ptr.write(Self { index, marker: PhantomPinned })) }
+///     }
+/// }
+///
+/// let len = 3;
+/// pin_boxed_slice_new!(nodes: [Node] with len = len, |i| init(i));
+/// assert_eq!(nodes.iter().map(|node| node.index).collect::<Vec<_>>(), [0, 1, 2]);
+/// ```
+#[cfg(feature = "alloc")]
+#[macro_export]
+macro_rules! pin_boxed_slice_new {
+    ($varn:ident: [$vart:ty] with len = $len:expr, $(|$index:ident|)? $methodn:ident($($arg:expr),* $(,)?)) => {
+        let $varn = $crate::pin_boxed_slice_new!(@expand $vart, $len, $(|$index|)? $methodn($($arg),*));
+    };
+    (mut $varn:ident: [$vart:ty] with len = $len:expr, $(|$index:ident|)? $methodn:ident($($arg:expr),* $(,)?)) => {
+        let mut $varn = $crate::pin_boxed_slice_new!(@expand $vart, $len, $(|$index|)? $methodn($($arg),*));
+    };
+    (@expand $vart:ty, $len:expr, $(|$index:ident|)? $methodn:ident($($arg:expr),*)) => {
+        $crate::init_boxed_slice::<$vart>($len, |__index, __elem_ptr| {
+            $(let $index = __index;)?
+            <$vart>::$methodn(__elem_ptr, $($arg),*)
+        })
+    };
+}
+/// Counts the fields a `pin_init!` block may track, i.e. the invocations of
+/// the tracking macros and the fields of each `pin_write_fields!`, so that
+/// the guard is sized at expansion time. Each token is counted by its own
+/// invocation, so the recursion is only as deep as the nesting of the block.
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __init_capacity {
+    (@tt pin_init_field) => { 1 };
+    (@tt pin_init_field_then) => { 1 };
+    (@tt pin_init_write) => { 1 };
+    (@tt pin_addr_guard) => { 1 };
+    // The arguments of `pin_write_fields!`, one colon per field.
+    (@tt ($this:ident { $($inner:tt)* })) => {
+        $crate::__init_capacity!($($inner)*) $(+ $crate::__init_capacity!(@colon $inner))*
+    };
+    (@tt ($($inner:tt)*)) => { $crate::__init_capacity!($($inner)*) };
+    (@tt [$($inner:tt)*]) => { $crate::__init_capacity!($($inner)*) };
+    (@tt {$($inner:tt)*}) => { $crate::__init_capacity!($($inner)*) };
+    (@tt $t:tt) => { 0 };
+    (@colon :) => { 1 };
+    (@colon $t:tt) => { 0 };
+    ($($t:tt)*) => { 0 $(+ $crate::__init_capacity!(@tt $t))* };
+}
+/// Defines the macros available inside `pin_init!` and `pin_try_init!`
+/// blocks. `$d` should be `$`.
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __pin_init_helpers {
+    (@return $d:tt $ptr:ident, $guard:ident [@result]) => {
+        /// Disarms the guard and returns the value from the method, which
+        /// has an explicit return type.
+        #[allow(unused_macros)]
+        macro_rules! pin_init_return {
+            ($value:expr) => {{
+                let __value = $value;
+                $guard.disarm();
+                return __value;
+            }};
+        }
+    };
+    (@return $d:tt $ptr:ident, $guard:ident [$($ok:tt)*]) => {
+        /// Disarms the guard and returns the pinned value from the method.
+        /// All fields must be initialized by then.
+        #[allow(unused_macros)]
+        macro_rules! pin_init_return {
+            () => {{
+                $guard.disarm();
+                return $($ok)*(unsafe { core::pin::Pin::new_unchecked(&mut *$ptr) });
+            }};
+        }
+    };
+    // The fields are accessed through `$this`, so that it stays usable in the
+    // block. `$ptr` itself is only used once `$this` is no longer (by the
+    // guard and to return the value).
+    ($d:tt $ptr:ident, $guard:ident, $this:ident [$($ok:tt)*]) => {
+        $crate::__pin_init_helpers!(@return $d $ptr, $guard [$($ok)*]);
+        /// Gets the `*mut Self` of the storage, e.g. to let a C function
+        /// initialize the value in place.
+        #[allow(unused_macros)]
+        macro_rules! pin_init_raw_ptr {
+            () => {
+                &mut *$this as *mut Self
+            };
+        }
+        /// Clones the potential result of this method. Should be used
+        /// Only to speculatively obtain pointers lying inside `Self`.
+        #[allow(unused_macros)]
+        macro_rules! pin_init_clone {
+            () => {
+                unsafe { core::pin::Pin::new_unchecked(&mut *$ptr) }
+            };
+        }
+        /// Gets `Pin<&mut MaybeUninit<F>>`, where `F` — owned immovable type.
+        /// If an initializer call follows, the field is initialized and
+        /// scheduled to be dropped on an early return or a panic, unless the
+        /// initializer returns another pin than the one of the field, which
+        /// panics.
+        #[allow(unused_macros)]
+        macro_rules! pin_init_field {
+            ($fieldn:ident: $fieldt:ty) => {{
+                $crate::__trace_init($ptr, stringify!($fieldn));
+                $guard.mark(unsafe { core::ptr::addr_of_mut!((*$ptr).$fieldn) });
+                unsafe { core::pin::Pin::new_unchecked($crate::transmute_maybe_uninit(&mut $this.$fieldn)) }
+            }};
+            ($fieldn:ident: $fieldt:ty = $methodn:ident($d($arg:expr),* $d(,)?)) => {{
+                let __value_ptr = unsafe { core::ptr::addr_of!((*$ptr).$fieldn) };
+                $crate::__assert_init(<$fieldt>::$methodn(pin_init_field!($fieldn: $fieldt), $d($arg),*), __value_ptr);
+                unsafe { $guard.push(|__ptr| core::ptr::drop_in_place(core::ptr::addr_of_mut!((*__ptr).$fieldn))) };
+            }};
+            ($fieldn:ident: $fieldt:ty = $methodn:ident($d($arg:expr),* $d(,)?) after $d($depn:ident),+ $d(,)?) => {{
+                pin_init_requires!($d($depn),+);
+                pin_init_field!($fieldn: $fieldt = $methodn($d($arg),*));
+            }};
+        }
+        /// Asserts that the fields are initialized already, for the
+        /// `debug-init` checks, e.g. before a field depending on them is.
+        #[allow(unused_macros)]
+        macro_rules! pin_init_requires {
+            ($d($depn:ident),+ $d(,)?) => {
+                $d({
+                    let __field_ptr = unsafe { core::ptr::addr_of!((*$ptr).$depn) };
+                    let __readable = {
+                        use $crate::{__ProbeBytes as _, __ProbeNoBytes as _};
+                        (&$crate::__BytesProbe::of(__field_ptr)).readable()
+                    };
+                    $guard.check_init(__field_ptr, __readable, stringify!($depn));
+                })+
+            };
+        }
+        /// Initializes a field like `pin_init_field!`, then passes the
+        /// resulting `Pin<&mut F>` to a closure for the follow-up setup,
+        /// returning its result. The field is already scheduled to be dropped
+        /// when the closure runs.
+        #[allow(unused_macros)]
+        macro_rules! pin_init_field_then {
+            ($fieldn:ident: $fieldt:ty = $methodn:ident($d($arg:expr),* $d(,)?), $then:expr) => {{
+                let __value_ptr = unsafe { core::ptr::addr_of!((*$ptr).$fieldn) };
+                let mut __field_ptr = <$fieldt>::$methodn(pin_init_field!($fieldn: $fieldt), $d($arg),*);
+                $crate::__assert_init(__field_ptr.as_mut(), __value_ptr);
+                unsafe { $guard.push(|__ptr| core::ptr::drop_in_place(core::ptr::addr_of_mut!((*__ptr).$fieldn))) };
+                ($then)(__field_ptr)
+            }};
+        }
+        /// Writes a field without dropping its previous (uninitialized)
+        /// value, and schedules it to be dropped on an early return or a
+        /// panic.
+        #[allow(unused_macros)]
+        macro_rules! pin_init_write {
+            ($fieldn:ident = $value:expr) => {{
+                let __value = $value;
+                $crate::__trace_init($ptr, stringify!($fieldn));
+                unsafe {
+                    core::ptr::write(core::ptr::addr_of_mut!($this.$fieldn), __value);
+                    $guard.mark(core::ptr::addr_of_mut!((*$ptr).$fieldn));
+                    $guard.push(|__ptr| core::ptr::drop_in_place(core::ptr::addr_of_mut!((*__ptr).$fieldn)));
+                }
+            }};
+        }
+        /// Writes several `Copy` fields in sequence, marking them as
+        /// initialized. Such fields have nothing to drop, so they are not
+        /// registered in the guard.
+        #[allow(unused_macros)]
+        macro_rules! pin_write_fields {
+            ($this_:ident { $d($fieldn:ident: $value:expr),* $d(,)? }) => {{
+                $d(
+                    let __value = $value;
+                    $crate::__trace_init($ptr, stringify!($fieldn));
+                    unsafe {
+                        $crate::__write_copy(core::ptr::addr_of_mut!($this_.$fieldn), __value);
+                        $guard.mark(core::ptr::addr_of_mut!((*$ptr).$fieldn));
+                    }
+                )*
+            }};
+        }
+        /// Writes a `PinAddr` field recording its own address, so that
+        /// `PinAddr::assert_pinned` can tell whether the value was moved.
+        #[allow(unused_macros)]
+        macro_rules! pin_addr_guard {
+            ($fieldn:ident) => {{
+                $crate::__trace_init($ptr, stringify!($fieldn));
+                unsafe {
+                    let __field_ptr: *mut $crate::PinAddr = core::ptr::addr_of_mut!($this.$fieldn);
+                    core::ptr::write(__field_ptr, $crate::PinAddr::__new(__field_ptr));
+                    $guard.mark(core::ptr::addr_of_mut!((*$ptr).$fieldn));
+                }
+            }};
+        }
+    };
+}
+/// Defines `Self::init` method, a replacement of the `Self::new` method. The
+/// method may be `async`, returning the pointer once the whole block is done.
+/// Wrapped in an `impl` block, the method is also exposed through [`PinInit`].
+/// The first lifetime is the storage lifetime of `Self`. Generic parameters
+/// following it are the method's own ones, and may be bound by a `where`
+/// clause before the block. An explicit `-> Type` makes the method return
+/// the value of the block instead, which is usually built around
+/// `pin_init_clone!()` in its final expression. `pin_init_return!()` returns
+/// early, once all fields are initialized (`pin_init_return!(value)` with an
+/// explicit return type).
+/// Prefixed with `boxed`, e.g. `pin_init!(pub boxed fn init<'a>(..) {..})`,
+/// the macro also defines `fn new_boxed(args) -> Pin<Box<Self>>`, which
+/// initializes the value on heap (requires the `alloc` feature). Another
+/// name may be given in parentheses, e.g. `boxed(with_boxed)`.
+/// Attributes and doc comments before `fn` are forwarded to the method, and
+/// the `cfg`, `cfg_attr`, `allow` and `deprecated` ones to the boxed method
+/// too, while the ones before `impl` are forwarded to both of the generated
+/// impls.
+/// ```
+/// # use std::{mem::MaybeUninit, pin::pin};
+/// # use pin_macros::pin_init;
+/// struct Point<'a> {
+///     coords: [u32; 3],
+///     this: Option<&'a Point<'a>>,
+/// }
+/// impl<'a> Point<'a> {
+///     pin_init!(fn init<'a>(this, x: u32, y: u32, z: u32) {
+///         this.coords = [x, y, z];
+///         this.this = None;
+///     });
+/// }
+///
+/// let mut uninit = MaybeUninit::uninit();
+/// let uninit_ptr = pin!(uninit);
+/// let point = Point::init(uninit_ptr, 1, 2, 3);
+/// assert_eq!(point.coords, [1, 2, 3]);
+///
+/// struct Cached {
+///     value: u64,
+///     hits: u32,
+/// }
+/// impl<'a> Cached {
+///     pin_init!(fn init<'a>(this, cached: Option<u64>) {
+///         this.hits = 0;
+///         if let Some(value) = cached {
+///             this.value = value;
+///             pin_init_return!();
+///         }
+///         this.value = 42;
+///     });
+/// }
+///
+/// let uninit = MaybeUninit::uninit();
+/// let uninit_ptr = pin!(uninit);
+/// let cached = Cached::init(uninit_ptr, Some(7));
+/// assert_eq!(cached.value, 7);
+/// ```
+///
+/// `pin_init_raw_ptr!()` gets the `*mut Self` of the storage instead, which
+/// may be passed to foreign code filling the value in place. Completing the
+/// block then asserts that the foreign code initialized every field, just like
+/// the fields written in Rust, so the call is only as safe as its contract.
+/// With the `debug-init` feature, a field of a `pin_struct!` type left holding
+/// nothing but the poison is still reported as forgotten.
+/// ```
+/// # use pin_macros::{pin_init, pin_new};
+/// #[repr(C)]
+/// struct Attr {
+///     flags: u32,
+///     size: u64,
+/// }
+/// // Stands for a C function initializing `*attr`.
+/// unsafe extern "C" fn attr_init(attr: *mut Attr) -> i32 {
+///     attr.write(Attr { flags: 1, size: 4096 });
+///     0
+/// }
+/// impl<'a> Attr {
+///     pin_init!(fn init<'a>(this) {
+///         // SAFETY: `attr_init` fully initializes the value on success.
+///         assert_eq!(unsafe { attr_init(pin_init_raw_ptr!()) }, 0);
+///     });
+/// }
+///
+/// pin_new!(attr: Attr = init());
+/// assert_eq!((attr.flags, attr.size), (1, 4096));
+/// ```
+///
+/// `pin_write_fields!(this { a: 1, b: f() })` writes several `Copy` fields at
+/// once, in order, marking them as initialized for the `debug-init` checks,
+/// e.g. the scalar fields of a mostly plain struct, while the other ones are
+/// initialized by `pin_init_field!` or `pin_init_write!` as usual.
+/// ```
+/// # use std::marker::PhantomPinned;
+/// # use pin_macros::{pin_init, pin_new};
+/// struct Buf {
+///     data: Vec<u8>,
+///     marker: PhantomPinned,
+/// }
+/// #[repr(C)]
+/// struct Packet {
+///     kind: u8,
+///     flags: u8,
+///     len: u16,
+///     crc: u32,
+///     payload: Buf,
+///     end: *const u8,
+/// }
+/// impl<'a> Packet {
+///     pin_init!(fn init<'a>(this, data: &[u8]) {
+///         pin_write_fields!(this {
+///             kind: 1,
+///             flags: 0,
+///             len: data.len() as u16,
+///             crc: data.iter().map(|&byte| byte as u32).sum(),
+///         });
+///         pin_init_write!(payload = Buf { data: data.to_vec(), marker: PhantomPinned });
+///         this.end = this.payload.data.as_ptr_range().end;
+///     });
+/// }
+///
+/// pin_new!(packet: Packet = init(b"ping"));
+/// assert_eq!((packet.kind, packet.flags, packet.len, packet.crc), (1, 0, 4, 430));
+/// assert_eq!(packet.end, packet.payload.data.as_ptr_range().end);
+/// ```
+///
+/// `pin_addr_guard!(field)` writes a [`PinAddr`] field recording its own
+/// address, which the methods check by `PinAddr::assert_pinned` to catch a
+/// moved value in tests with the `test-pin-invariants` feature.
+///
+/// The fields are initialized in the order of the block, so a field built
+/// from another one, e.g. a table sized by a capacity, must follow it. With
+/// the `debug-init` feature, the dependencies may be checked in debug builds:
+/// `pin_init_field!(b: B = init(..) after a, c)` asserts that `a` and `c` are
+/// initialized before `b` is, and `pin_init_requires!(a, c)` does the same
+/// anywhere in the block, e.g. before reading them through `this`. A field is
+/// taken as initialized once it is marked by the `pin_init_xxx!` macros or,
+/// if its type has no padding, e.g. an integer or a pointer, holds anything
+/// but poison. The bytes of the other types can't be read safely, so such
+/// an unmarked field is assumed to be initialized.
+/// ```
+/// # use std::marker::PhantomPinned;
+/// # use pin_macros::{pin_init, pin_new};
+/// struct Table {
+///     rows: Vec<u32>,
+///     marker: PhantomPinned,
+/// }
+/// impl<'a> Table {
+///     pin_init!(fn init<'a>(this, capacity: usize) {
+///         pin_init_write!(rows = Vec::with_capacity(capacity));
+///         this.marker = PhantomPinned;
+///     });
+/// }
+/// struct Db {
+///     capacity: usize,
+///     table: Table,
+/// }
+/// impl<'a> Db {
+///     pin_init!(fn init<'a>(this, capacity: usize) {
+///         this.capacity = capacity;
+///         pin_init_field!(table: Table = init(this.capacity) after capacity);
+///     });
+///     // The dependency is violated: the table is sized by a capacity, which
+///     // is not initialized yet.
+///     pin_init!(fn init_misordered<'a>(this, capacity: usize) {
+///         pin_init_field!(table: Table = init(this.capacity) after capacity);
+///         this.capacity = capacity;
+///     });
+/// }
+///
+/// pin_new!(db: Db = init(16));
+/// assert!(db.table.rows.capacity() >= db.capacity);
+///
+/// # #[cfg(all(feature = "debug-init", debug_assertions))]
+/// let result = std::panic::catch_unwind(|| {
+///     pin_new!(db: Db = init_misordered(16));
+/// });
+/// # #[cfg(all(feature = "debug-init", debug_assertions))]
+/// assert!(result.is_err());
+/// ```
+///
+/// The lifetimes of `Self` are distinct from the storage lifetime, so a
+/// reference to a context outliving the value, e.g. `&'ctx Context`, flows
+/// from an argument into the fields, including the ones initialized by
+/// `pin_init_field!` and `pin_field_init!`. The storage may be borrowed for
+/// a shorter lifetime than `'ctx` then (as implied by `Pin<&'a mut Self>`).
+/// ```
+/// # use std::{marker::PhantomPinned, pin::Pin};
+/// # use pin_macros::{pin_field_init, pin_init, pin_new, PinOption};
+/// struct Context {
+///     name: &'static str,
+/// }
+/// struct Conn<'ctx> {
+///     ctx: &'ctx Context,
+///     marker: PhantomPinned,
+/// }
+/// impl<'a, 'ctx> Conn<'ctx> {
+///     pin_init!(fn init<'a>(this, ctx: &'ctx Context) {
+///         this.ctx = ctx;
+///         this.marker = PhantomPinned;
+///     });
+/// }
+/// struct Server<'ctx> {
+///     main: Conn<'ctx>,
+///     spare: PinOption<Conn<'ctx>>,
+/// }
+/// impl<'a, 'ctx> Server<'ctx> {
+///     pin_init!(fn init<'a>(this, ctx: &'ctx Context) {
+///         pin_init_field!(main: Conn<'ctx> = init(ctx));
+///         pin_init_write!(spare = PinOption::none());
+///     });
+///     fn open_spare(mut self: Pin<&mut Self>) -> &'ctx Context {
+///         let ctx = self.main.ctx;
+///         pin_field_init!(Conn<'ctx>: init(self.spare, ctx));
+///         ctx
+///     }
+/// }
+///
+/// let ctx = Context { name: "db" };
+/// let ctx_ref = {
+///     pin_new!(mut server: Server = init(&ctx));
+///     server.as_mut().open_spare()
+/// };
+/// assert_eq!(ctx_ref.name, "db");
+/// ```
+///
+/// Except for `async` ones, the methods are `#[track_caller]`, so a panic
+/// during the initialization, e.g. of an assertion in the block or of a
+/// `debug-init` check, reports the line calling the initializer.
+/// ```
+/// # use std::{panic::{self, Location}, sync::Mutex};
+/// # use pin_macros::{pin_init, pin_new};
+/// struct Ratio {
+///     denom: u32,
+/// }
+/// impl<'a> Ratio {
+///     pin_init!(fn init<'a>(this, denom: u32) {
+///         assert_ne!(denom, 0);
+///         this.denom = denom;
+///     });
+/// }
+///
+/// static LINE: Mutex<u32> = Mutex::new(0);
+/// panic::set_hook(Box::new(|info| *LINE.lock().unwrap() = info.location().unwrap().line()));
+/// let line = Location::caller().line() + 1;
+/// let result = panic::catch_unwind(|| { pin_new!(ratio: Ratio = init(0)); });
+/// assert!(result.is_err());
+/// assert_eq!(*LINE.lock().unwrap(), line);
+/// ```
+///
+/// A method without the storage lifetime or without the name of the value
+/// before its arguments fails with a hint on the expected signature.
+/// ```compile_fail
+/// # use pin_macros::pin_init;
+/// struct Counter {
+///     count: u32,
+/// }
+/// impl<'a> Counter {
+///     pin_init!(fn init<'a>(count: u32) {
+///         this.count = count;
+///     });
+/// }
+/// ```
+#[macro_export]
+macro_rules! pin_init {
+    ($(#[$implattr:meta])* impl<$($gen:lifetime),* $(,)?> $selft:ty {
+        $(#[$attr:meta])* $v:vis fn $name:ident<$a:lifetime>($this:ident $(, $($argn:ident: $argt:ty),+)? $(,)?) {$($blk:tt)*}
+    }) => {
+        $(#[$implattr])*
+        impl<$($gen),*> $selft {
+            $crate::pin_init!(@expand($(#[$attr])* #[track_caller] $v fn) $name<$a>($this $(, $($argn: $argt),+)?) {$($blk)*});
+        }
+        $(#[$implattr])*
+        impl<$($gen),*> $crate::PinInit<$a, ($($($argt,)+)?)> for $selft {
+            #[track_caller]
+            fn init(
+                __uninit_ptr: core::pin::Pin<&$a mut core::mem::MaybeUninit<Self>>,
+                ($($($argn,)+)?): ($($($argt,)+)?),
+            ) -> core::pin::Pin<&$a mut Self> {
+                <$selft>::$name(__uninit_ptr, $($($argn),+)?)
+            }
+        }
+    };
+    ($(#[$implattr:meta])* impl $selft:ty { $($item:tt)* }) => {
+        $crate::pin_init!($(#[$implattr])* impl<> $selft { $($item)* });
+    };
+    ($(#[$($attr:tt)*])* $v:vis boxed $(($boxedn:ident))? fn $name:ident<$a:lifetime $(, $gk:tt $($gn:ident)? $(: $($gl:lifetime)? $($gt:path)?)?)*>(
+        $this:ident $(, $($argn:ident: $argt:ty),+)? $(,)?
+    ) $($rest:tt)+) => {
+        $crate::pin_init!($(#[$($attr)*])* $v fn $name<$a $(, $gk $($gn)? $(: $($gl)? $($gt)?)?)*>($this $(, $($argn: $argt),+)?) $($rest)+);
+        $crate::pin_init!(@boxed_attrs [] [$(#[$($attr)*])*] ($v [new_boxed $($boxedn)?] $name[$($gk $($gn)? $(: $($gl)? $($gt)?)?),*]($($($argn: $argt),+)?)) {} $($rest)+);
+    };
+    // The boxed method keeps the attributes deciding whether and how it may
+    // be used only, not the doc comments or the ones changing the code.
+    (@boxed_attrs [$($kept:tt)*] [#[cfg $($attr:tt)*] $($attrs:tt)*] $($rest:tt)*) => {
+        $crate::pin_init!(@boxed_attrs [$($kept)* #[cfg $($attr)*]] [$($attrs)*] $($rest)*);
+    };
+    (@boxed_attrs [$($kept:tt)*] [#[cfg_attr $($attr:tt)*] $($attrs:tt)*] $($rest:tt)*) => {
+        $crate::pin_init!(@boxed_attrs [$($kept)* #[cfg_attr $($attr)*]] [$($attrs)*] $($rest)*);
+    };
+    (@boxed_attrs [$($kept:tt)*] [#[allow $($attr:tt)*] $($attrs:tt)*] $($rest:tt)*) => {
+        $crate::pin_init!(@boxed_attrs [$($kept)* #[allow $($attr)*]] [$($attrs)*] $($rest)*);
+    };
+    (@boxed_attrs [$($kept:tt)*] [#[deprecated $($attr:tt)*] $($attrs:tt)*] $($rest:tt)*) => {
+        $crate::pin_init!(@boxed_attrs [$($kept)* #[deprecated $($attr)*]] [$($attrs)*] $($rest)*);
+    };
+    (@boxed_attrs [$($kept:tt)*] [#[$($attr:tt)*] $($attrs:tt)*] $($rest:tt)*) => {
+        $crate::pin_init!(@boxed_attrs [$($kept)*] [$($attrs)*] $($rest)*);
+    };
+    (@boxed_attrs [$($kept:tt)*] [] ($($emit:tt)*) $($rest:tt)*) => {
+        $crate::pin_init!(@boxed([$($kept)*] $($emit)*) $($rest)*);
+    };
+    (@boxed($attrs:tt $v:vis [$_default:ident $boxedn:ident] $($rest:tt)*) {$($where:tt)*} $blk:block) => {
+        $crate::pin_init!(@boxed($attrs $v [$boxedn] $($rest)*) {$($where)*} $blk);
+    };
+    (@boxed([$($attr:tt)*] $v:vis [$boxedn:ident] $name:ident[$($gen:tt)*]($($argn:ident: $argt:ty),*)) {$($where:tt)*} $blk:block) => {
+        #[doc = concat!("Initializes the value on heap through `Self::", stringify!($name), "`.")]
+        $($attr)*
+        // Calls the method, which is deprecated along with this one, if at all.
+        #[allow(deprecated)]
+        $v fn $boxedn<$($gen)*>($($argn: $argt),*) -> core::pin::Pin<$crate::__alloc::Box<Self>> $($where)* {
+            $crate::pin_box_new!(__boxed: Self = $name($($argn),*));
+            __boxed
+        }
+    };
+    (@boxed($($emit:tt)*) {} -> $($rest:tt)+) => {
+        compile_error!("`boxed` requires the default return type");
+    };
+    (@boxed($($emit:tt)*) {$($where:tt)*} $t:tt $($rest:tt)+) => {
+        $crate::pin_init!(@boxed($($emit)*) {$($where)* $t} $($rest)+);
+    };
+    ($(#[$attr:meta])* $v:vis fn $name:ident $($rest:tt)*) => {
+        $crate::pin_init!(@expand($(#[$attr])* #[track_caller] $v fn) $name $($rest)*);
+    };
+    ($(#[$attr:meta])* $v:vis async fn $name:ident $($rest:tt)*) => {
+        $crate::pin_init!(@expand($(#[$attr])* $v async fn) $name $($rest)*);
+    };
+    (@expand($($head:tt)*) $name:ident<$a:lifetime $(, $gk:tt $($gn:ident)? $(: $($gl:lifetime)? $($gt:path)?)?)*>(
+        $this:ident $(, $($argn:ident: $argt:ty),+)? $(,)?
+    ) $($rest:tt)+) => {
+        $crate::pin_init!(@ret(($($head)*) $name[$($gk $($gn)? $(: $($gl)? $($gt)?)?),*](
+            $a, $this, $($($argn: $argt),+)?
+        )) $a $($rest)+);
+    };
+    (@expand($($head:tt)*) $name:ident<$a:lifetime $($rest:tt)*) => {
+        compile_error!(
+            "expected the name of the initialized value before the typed arguments, \
+             e.g. `fn init<'a>(this, len: usize)`"
+        );
+    };
+    (@expand($($head:tt)*) $name:ident $($rest:tt)*) => {
+        compile_error!("the storage lifetime must be declared first, e.g. `fn init<'a>(this)`");
+    };
+    (@ret($($emit:tt)*) $a:lifetime -> $($rest:tt)+) => {
+        $crate::pin_init!(@ret_ty($($emit)*) [] $($rest)+);
+    };
+    (@ret($($emit:tt)*) $a:lifetime $($rest:tt)+) => {
+        $crate::pin_init!(@where($($emit)* -> [core::pin::Pin<&$a mut Self>] []) {} $($rest)+);
+    };
+    (@ret_ty($($emit:tt)*) [$($ret:tt)*] where $($rest:tt)+) => {
+        $crate::pin_init!(@where($($emit)* -> [$($ret)*] [@result]) {} where $($rest)+);
+    };
+    (@ret_ty($($emit:tt)*) [$($ret:tt)*] {$($blk:tt)*}) => {
+        $crate::pin_init!(@emit($($emit)* -> [$($ret)*] [@result]) [] {$($blk)*});
+    };
+    (@ret_ty($($emit:tt)*) [$($ret:tt)*] $t:tt $($rest:tt)+) => {
+        $crate::pin_init!(@ret_ty($($emit)*) [$($ret)* $t] $($rest)+);
+    };
+    (@where($($emit:tt)*) {$($where:tt)*} {$($blk:tt)*}) => {
+        $crate::pin_init!(@emit($($emit)*) [$($where)*] {$($blk)*});
+    };
+    (@where($($emit:tt)*) {$($where:tt)*} $t:tt $($rest:tt)+) => {
+        $crate::pin_init!(@where($($emit)*) {$($where)* $t} $($rest)+);
+    };
+    (@emit(($($head:tt)*) $name:ident[$($gen:tt)*](
+        $a:lifetime, $this:ident, $($args:tt)*
+    ) -> [$($ret:tt)*] [$($ok:tt)*]) [$($where:tt)*] {$($blk:tt)*}) => {
+        $($head)* $name<$($gen)*>(
+            mut __uninit_ptr: core::pin::Pin<&$a mut core::mem::MaybeUninit<Self>>,
+            $($args)*
+        ) -> $($ret)* $($where)* {
+            let __init_ptr = unsafe { __uninit_ptr.as_mut().get_unchecked_mut().as_mut_ptr() };
+            let mut __init_guard = unsafe {
+                $crate::InitGuard::<Self, { $crate::__init_capacity!($($blk)*) }>::with_capacity(__init_ptr)
+            };
+            __init_guard.set_fields({
+                use $crate::{__ProbeFields as _, __ProbeNoFields as _};
+                (&$crate::__FieldsProbe::<Self>(core::marker::PhantomData)).fields()
+            });
+            unsafe {
+                use $crate::{__ProbeNoPoison as _, __ProbePoison as _};
+                __init_guard.set_poison((&$crate::__PoisonProbe::<Self>(core::marker::PhantomData)).poison());
+            }
+            #[allow(unused_variables)]
+            let $this = unsafe { &mut *__init_ptr };
+            $crate::__pin_init_helpers!($ __init_ptr, __init_guard, $this [$($ok)*]);
 
-/// Initializes owned immovable value on stack.
+            $crate::pin_init!(@finish [$($ok)*] __init_ptr, __init_guard, $this, {$($blk)*})
+        }
+    };
+    (@finish [@result] $ptr:ident, $guard:ident, $this:ident, $blk:block) => {{
+        let __result = $blk;
+        $guard.disarm();
+        __result
+    }};
+    (@finish [$($ok:tt)*] $ptr:ident, $guard:ident, $this:ident, $blk:block) => {{
+        $blk;
+        $guard.disarm();
+        $($ok)*(unsafe { core::pin::Pin::new_unchecked(&mut *$ptr) })
+    }};
+}
+/// Implements `Drop` forwarding to `Self::drop_pinned(self: Pin<&mut Self>)`,
+/// which is defined by the user, so the self-references may be torn down
+/// through the pinned getters. Lifetimes of `Self` are declared after `impl`
+/// like in `pin_init!`.
+///
+/// Re-pinning `&mut self` in `Drop::drop` is sound, since the value is never
+/// used again after being dropped, so it can't be moved after the pinned
+/// drop either. `drop_pinned` itself can't move the fields out, as it only
+/// gets `Pin<&mut Self>`.
+/// ```
+/// # use std::{cell::RefCell, marker::PhantomPinned, pin::Pin};
+/// # use pin_macros::pin_drop;
+/// struct Conn<'a> {
+///     log: &'a RefCell<Vec<&'static str>>,
+///     marker: PhantomPinned,
+/// }
+/// impl Conn<'_> {
+///     fn drop_pinned(self: Pin<&mut Self>) {
+///         self.log.borrow_mut().push("closed");
+///     }
+/// }
+/// pin_drop!(impl<'a> Conn<'a>);
+///
+/// let log = RefCell::new(Vec::new());
+/// drop(Box::pin(Conn { log: &log, marker: PhantomPinned }));
+/// assert_eq!(*log.borrow(), ["closed"]);
+/// ```
 #[macro_export]
-macro_rules! pin_new {
-    ($varn:ident: $vart:ty = $methodn:ident($($arg:expr),* $(,)?)) => {
-        let mut __uninit = std::mem::MaybeUninit::<$vart>::uninit();
-        let __uninit_ptr = std::pin::pin!(__uninit);
-        let $varn = <$vart>::init(__uninit_ptr, $($($arg),*)?);
+macro_rules! pin_drop {
+    ($(#[$attr:meta])* impl<$($gen:lifetime),* $(,)?> $selft:ty) => {
+        $(#[$attr])*
+        impl<$($gen),*> Drop for $selft {
+            fn drop(&mut self) {
+                <$selft>::drop_pinned(unsafe { core::pin::Pin::new_unchecked(self) });
+            }
+        }
     };
-    (mut $varn:ident: $vart:ty = $methodn:ident($($arg:expr),* $(,)?)) => {
-        let mut __uninit = std::mem::MaybeUninit::<$vart>::uninit();
-        let __uninit_ptr = std::pin::pin!(__uninit);
-        let mut $varn = <$vart>::init(__uninit_ptr, $($($arg),*)?);
+    ($(#[$attr:meta])* impl $selft:ty) => {
+        $crate::pin_drop!($(#[$attr])* impl<> $selft);
     };
 }
-/// Defines `Self::init` method, a replacement of the `Self::new` method.
+/// Implements a node of an intrusive doubly-linked list for a struct with
+/// `next: Option<NonNull<Self>>` and `prev: Option<NonNull<Self>>` fields,
+/// e.g. `pin_intrusive_node!(impl<'a> Node<'a> { pub value: u32 })`, which
+/// generates the pinned payload getter `value` (see `field_pin!`), the
+/// unsafe `next`/`prev` traversal through `Pin<&Self>` and `link_after`,
+/// `is_linked` and `unlink`. Lifetimes of `Self` are declared after `impl`
+/// like in `pin_drop!`.
+///
+/// Since the nodes are pinned, the pointers stay valid as long as each node
+/// unlinks itself before its memory is reused, so the implemented `Drop`
+/// unlinks the node, and the type must not be `Unpin` (otherwise the
+/// compilation fails with "type annotations needed"). Hence the nodes must
+/// be stored so that they are dropped in place, e.g. by `Box::pin` or
+/// `pin_new_guarded!`, but not by `pin_new!`, whose storage is never
+/// dropped, which `link_after` leaves to the caller. The neighbour returned
+/// by `next`/`prev` is only borrowed through `self`, so the caller also
+/// ensures it is not unlinked or dropped while the reference is in use.
+/// ```
+/// # use std::{marker::PhantomPinned, pin::Pin, ptr::NonNull};
+/// # use pin_macros::pin_intrusive_node;
+/// struct Node {
+///     value: u32,
+///     next: Option<NonNull<Self>>,
+///     prev: Option<NonNull<Self>>,
+///     marker: PhantomPinned,
+/// }
+/// pin_intrusive_node!(impl Node { value: u32 });
+///
+/// fn node(value: u32) -> Pin<Box<Node>> {
+///     Box::pin(Node { value, next: None, prev: None, marker: PhantomPinned })
+/// }
+/// fn values(mut node: Option<Pin<&Node>>) -> Vec<u32> {
+///     let mut values = Vec::new();
+///     while let Some(current) = node {
+///         values.push(current.value);
+///         // SAFETY: the list is not modified while it is traversed.
+///         node = unsafe { current.next() };
+///     }
+///     values
+/// }
+///
+/// let (mut a, mut b, mut c) = (node(1), node(2), node(3));
+/// // SAFETY: the nodes are boxed, so they are dropped in place.
+/// unsafe {
+///     a.as_mut().link_after(c.as_mut());
+///     a.as_mut().link_after(b.as_mut());
+/// }
+/// assert_eq!(values(Some(a.as_ref())), [1, 2, 3]);
+/// // SAFETY: `b` is alive and not modified while the reference is in use.
+/// assert_eq!(unsafe { c.as_ref().prev() }.map(|prev| prev.value), Some(2));
+///
+/// b.as_mut().unlink();
+/// assert!(!b.is_linked());
+/// assert_eq!(values(Some(a.as_ref())), [1, 3]);
+/// drop(c);
+/// assert_eq!(values(Some(a.as_ref())), [1]);
+/// ```
+///
+/// Without the `PhantomPinned` field the node could be moved out of the list.
+/// ```compile_fail
+/// # use std::ptr::NonNull;
+/// # use pin_macros::pin_intrusive_node;
+/// struct Node {
+///     value: u32,
+///     next: Option<NonNull<Self>>,
+///     prev: Option<NonNull<Self>>,
+/// }
+/// pin_intrusive_node!(impl Node { value: u32 });
+/// ```
 #[macro_export]
-macro_rules! pin_init {
-    ($v:vis fn $name:ident<$a:lifetime>($this:ident $(, $($argn:ident: $argt:ty),+)? $(,)?) $blk:block) => {
-        $v fn $name(
-            mut __uninit_ptr: std::pin::Pin<&$a mut std::mem::MaybeUninit<Self>>,
-            $($($argn: $argt)+)?
-        ) -> std::pin::Pin<&$a mut Self> {
-            let __init_ptr = unsafe { __uninit_ptr.as_mut().get_unchecked_mut().as_mut_ptr() };
+macro_rules! pin_intrusive_node {
+    ($(#[$attr:meta])* impl<$($gen:lifetime),* $(,)?> $selft:ty { $v:vis $payload:ident: $payloadt:ty $(,)? }) => {
+        $(#[$attr])*
+        impl<$($gen),*> $selft {
+            $crate::field_pin!($v $payload: $payloadt);
 
-            /// Clones the potential result of this method. Should be used
-            /// Only to speculatively obtain pointers lying inside `Self`.
-            macro_rules! pin_init_clone {
-                () => {
-                    unsafe { std::pin::Pin::new_unchecked(&mut *__init_ptr) }
-                };
+            /// Returns the next node, if any.
+            ///
+            /// # Safety
+            ///
+            /// The returned reference borrows `self` only, so the next node
+            /// must not be unlinked, relinked or dropped while it is in use.
+            $v unsafe fn next(self: core::pin::Pin<&Self>) -> Option<core::pin::Pin<&Self>> {
+                self.get_ref().next.map(|next| core::pin::Pin::new_unchecked(&*next.as_ptr()))
             }
-            /// Gets `Pin<&mut MaybeUninit<F>>`, where `F` — owned immovable type.
-            macro_rules! pin_init_field {
-                ($fieldn:ident: $fieldt:ty) => {
-                    unsafe { std::pin::Pin::new_unchecked($crate::transmute_maybe_uninit(&mut (*__init_ptr).$fieldn)) }
-                };
+            /// Returns the previous node, if any.
+            ///
+            /// # Safety
+            ///
+            /// The returned reference borrows `self` only, so the previous
+            /// node must not be unlinked, relinked or dropped while it is in
+            /// use.
+            $v unsafe fn prev(self: core::pin::Pin<&Self>) -> Option<core::pin::Pin<&Self>> {
+                self.get_ref().prev.map(|prev| core::pin::Pin::new_unchecked(&*prev.as_ptr()))
+            }
+            /// Checks whether the node has any neighbours.
+            $v fn is_linked(&self) -> bool {
+                self.next.is_some() || self.prev.is_some()
             }
+            /// Inserts `node` right after `self`. Panics if `node` is
+            /// already linked, since its neighbours would be left dangling.
+            ///
+            /// # Safety
+            ///
+            /// Both nodes must be dropped before their memory is reused, so
+            /// that they unlink themselves, e.g. by being stored in
+            /// `Box::pin` or `pin_new_guarded!`, but not `pin_new!`. No
+            /// reference obtained by `next`/`prev` to the node after `self`
+            /// may be in use.
+            #[track_caller]
+            $v unsafe fn link_after(mut self: core::pin::Pin<&mut Self>, mut node: core::pin::Pin<&mut Self>) {
+                assert!(!node.is_linked(), "the node is already linked");
+                let __self_ptr = core::ptr::NonNull::from(unsafe { self.as_mut().get_unchecked_mut() });
+                let __node_ptr = core::ptr::NonNull::from(unsafe { node.as_mut().get_unchecked_mut() });
+                let __after = self.next;
 
-            let $this = unsafe { &mut *__init_ptr };
-            $blk;
-            unsafe { std::pin::Pin::new_unchecked($this) }
+                $crate::pin_field_init!(node: |next => prev| {
+                    *next = __after;
+                    __self_ptr
+                });
+                if let Some(after) = __after {
+                    unsafe { (*after.as_ptr()).prev = Some(__node_ptr) };
+                }
+                $crate::pin_field_set!(self.next = __node_ptr);
+            }
+            /// Removes the node from its list, linking its neighbours to
+            /// each other. Does nothing if the node is not linked.
+            $v fn unlink(self: core::pin::Pin<&mut Self>) {
+                let this = unsafe { self.get_unchecked_mut() };
+                let (prev, next) = (this.prev.take(), this.next.take());
+
+                if let Some(prev) = prev {
+                    unsafe { (*prev.as_ptr()).next = next };
+                }
+                if let Some(next) = next {
+                    unsafe { (*next.as_ptr()).prev = prev };
+                }
+            }
+        }
+        $(#[$attr])*
+        impl<$($gen),*> Drop for $selft {
+            fn drop(&mut self) {
+                let _ = <Self as $crate::__MustNotBeUnpin<_>>::check;
+                unsafe { core::pin::Pin::new_unchecked(self) }.unlink();
+            }
         }
     };
+    ($(#[$attr:meta])* impl $selft:ty { $($payload:tt)* }) => {
+        $crate::pin_intrusive_node!($(#[$attr])* impl<> $selft { $($payload)* });
+    };
 }
 /// Generic utility for initializing optional fields of an immovable value
 /// after value's primary initialization. Rules summaries:
-/// 1. Initializes field of owned immovable type;
+/// 1. Initializes [`PinOption`] field of owned immovable type;
 /// 2. Initializes self-referencing field from an array of already initialized
-/// field value references;
-/// 3. A special simpliest case for the 2nd rule.
+///    field value references;
+/// 3. A special simpliest case for the 2nd rule;
+/// 4. Same as the 2nd rule, but the source fields are `*const F` or `*mut F`
+///    pointers, e.g. `|*const head, *mut tail => links|`, so no references to
+///    them are held at once.
+///
+/// For an array field, e.g. `[Node; N]: init(self.nodes)`, the 1st rule calls
+/// the initializer for each element (see [`init_array`]), and an identifier
+/// between pipes before the initializer, e.g. `|i| init(self.nodes, i)`,
+/// binds the element index. If an initializer panics, or returns another pin
+/// than the one of its element, the already initialized elements are dropped.
+///
+/// The 1st rule panics if the initializer returns another pin than the one of
+/// the field, which is left `None` then.
+///
+/// In debug builds, the 1st rule panics if the field is already `Some`,
+/// whose value would be leaked otherwise (see `pin_field_reinit!`).
+/// ```should_panic
+/// # use std::{marker::PhantomPinned, mem::MaybeUninit, pin::Pin};
+/// # use pin_macros::{pin_field_init, pin_init, pin_new, PinOption};
+/// struct Inner(PhantomPinned);
+/// impl Inner {
+///     fn init(ptr: Pin<&mut MaybeUninit<Self>>) -> Pin<&mut Self> {
+///         unsafe { Pin::new_unchecked(ptr.get_unchecked_mut().write(Self(PhantomPinned))) }
+///     }
+/// }
+/// struct Outer {
+///     inner: PinOption<Inner>,
+/// }
+/// impl<'a> Outer {
+///     pin_init!(fn init<'a>(this) {
+///         pin_init_write!(inner = PinOption::none());
+///     });
+/// }
+///
+/// pin_new!(mut outer: Outer = init());
+/// pin_field_init!(Inner: init(outer.inner));
+/// # #[cfg(not(debug_assertions))]
+/// # panic!();
+/// pin_field_init!(Inner: init(outer.inner));
+/// ```
+///
+/// The declared type must be the type of the field (or of its `PinOption`
+/// payload), otherwise the compilation fails, since the reference to the
+/// field is only cast to a pointer of the declared type.
+/// ```compile_fail
+/// # use std::{mem::MaybeUninit, pin::Pin};
+/// # use pin_macros::{pin_field_init, PinOption};
+/// struct Wide(u64);
+/// impl Wide {
+///     fn init(ptr: Pin<&mut MaybeUninit<Self>>) -> Pin<&mut Self> {
+///         unsafe { Pin::new_unchecked(ptr.get_unchecked_mut().write(Self(0))) }
+///     }
+/// }
+/// struct Outer {
+///     narrow: PinOption<u8>,
+/// }
+/// fn start(mut outer: Pin<&mut Outer>) {
+///     pin_field_init!(Wide: init(outer.narrow));
+/// }
+/// ```
+///
+/// Prefixed with `unsafe`, the 1st rule initializes a plain field of owned
+/// immovable type instead, overwriting its previous value without dropping
+/// it. The caller must ensure the field was not initialized yet (or may be
+/// leaked), and was not read before. An initializer returning another pin
+/// than the one of the field panics as well.
+///
+/// For a child on heap, e.g. of a recursive tree, the 1st rule with
+/// `Box<F>` as the declared type, e.g. `pin_field_init!(Box<Node>:
+/// init(self.left))`, initializes the value inside a new box, and stores it
+/// into an `Option<Pin<Box<F>>>` field (a plain `Box<F>` would let the value
+/// be moved out), returning the previous value like `Option::replace`. If
+/// the initializer panics, or returns another pin than the one of the box,
+/// the allocation is freed, and the field is left untouched. Requires the
+/// `alloc` feature.
+/// ```
+/// # use std::{marker::PhantomPinned, mem::MaybeUninit, panic, pin::Pin, ptr};
+/// # use pin_macros::{pin_field_init, pin_init, pin_new};
+/// struct Node {
+///     depth: u32,
+///     parent: *const Node,
+///     left: Option<Pin<Box<Node>>>,
+///     right: Option<Pin<Box<Node>>>,
+///     marker: PhantomPinned,
+/// }
+/// impl<'a> Node {
+///     pin_init!(fn init<'a>(this, parent: *const Node, depth: u32) {
+///         assert!(depth < 8, "the tree is too deep");
+///         this.depth = depth;
+///         this.parent = parent;
+///         pin_init_write!(left = None);
+///         pin_init_write!(right = None);
+///         this.marker = PhantomPinned;
+///     });
+///     fn grow(mut self: Pin<&mut Self>, depth: u32) {
+///         let (this, depth) = (&*self as *const Node, self.depth + depth);
+///         pin_field_init!(Box<Node>: init(self.left, this, depth));
+///     }
+/// }
+///
+/// pin_new!(mut root: Node = init(ptr::null(), 0));
+/// root.as_mut().grow(1);
+/// let left = root.left.as_ref().unwrap();
+/// assert_eq!(left.depth, 1);
+/// assert!(ptr::eq(left.parent, &*root));
+///
+/// let result = panic::catch_unwind(panic::AssertUnwindSafe(|| root.as_mut().grow(8)));
+/// assert!(result.is_err());
+/// assert_eq!(root.left.as_ref().unwrap().depth, 1);
+/// ```
 #[macro_export]
 macro_rules! pin_field_init {
-    ($fieldt:ty: $methodn:ident($this:ident.$fieldn:ident $(, $($arg:expr)+)? $(,)?)) => {
-        unsafe {
-            let __field_ptr = &mut $this.as_mut().get_unchecked_mut().$fieldn as *mut Option<$fieldt>;
-            *__field_ptr = Some(std::mem::MaybeUninit::uninit().assume_init());
-
-            match &mut *__field_ptr {
-                Some(__field) => {
-                    let __pinned_field = std::pin::Pin::new_unchecked($crate::transmute_maybe_uninit(__field));
-                    <$fieldt>::$methodn(__pinned_field, $($($arg)+)?);
-                },
-                None => unreachable!(),
-            }
-        }
+    (Box<$fieldt:ty>: $methodn:ident($this:ident.$fieldn:ident $(, $($arg:expr),+)? $(,)?)) => {{
+        $crate::__trace_init(&*$this, stringify!($fieldn));
+        let __uninit = $crate::UninitBox::<$fieldt>::new();
+        $crate::__assert_init(<$fieldt>::$methodn(unsafe { __uninit.as_uninit_pin() }, $($($arg),+)?), __uninit.as_ptr());
+        let __boxed = unsafe { __uninit.assume_init() };
+        $crate::__replace_unpin(unsafe { &mut $this.as_mut().get_unchecked_mut().$fieldn }, __boxed)
+    }};
+    (unsafe [$elemt:ty; $len:expr]: $(|$index:ident|)? $methodn:ident($this:ident.$fieldn:ident $(, $($arg:expr),+)? $(,)?)) => {{
+        $crate::__trace_init(&*$this, stringify!($fieldn));
+        let __field_ptr = unsafe { &mut $this.as_mut().get_unchecked_mut().$fieldn as *mut [$elemt; $len] };
+        let __uninit_ptr = unsafe { core::pin::Pin::new_unchecked($crate::transmute_maybe_uninit(&mut *__field_ptr)) };
+        $crate::pin_field_init!(@array __uninit_ptr, $elemt, $(|$index|)? $methodn($($($arg),+)?));
+    }};
+    ([$elemt:ty; $len:expr]: $(|$index:ident|)? $methodn:ident($this:ident.$fieldn:ident $(, $($arg:expr),+)? $(,)?)) => {{
+        $crate::__trace_init(&*$this, stringify!($fieldn));
+        let __field_ptr = unsafe {
+            &mut $this.as_mut().get_unchecked_mut().$fieldn as *mut $crate::PinOption<[$elemt; $len]>
+        };
+        $crate::__assert_none!(__field_ptr, $fieldn);
+        let __uninit_ptr = unsafe { core::pin::Pin::new_unchecked(&mut *__field_ptr).uninit_ptr() };
+        $crate::pin_field_init!(@array __uninit_ptr, $elemt, $(|$index|)? $methodn($($($arg),+)?));
+        unsafe { core::pin::Pin::new_unchecked(&mut *__field_ptr).assume_some() };
+    }};
+    (@array $uninit_ptr:ident, $elemt:ty, $(|$index:ident|)? $methodn:ident($($arg:expr),*)) => {
+        let __init = |__index, __elem_ptr| {
+            $(let $index = __index;)?
+            <$elemt>::$methodn(__elem_ptr, $($arg),*)
+        };
+        // The storage is only passed on to the initializer, which returns it.
+        unsafe { $crate::__init_array($uninit_ptr, __init) };
     };
+    (unsafe $fieldt:ty: $methodn:ident($this:ident.$fieldn:ident $(, $($arg:expr),+)? $(,)?)) => {{
+        $crate::__trace_init(&*$this, stringify!($fieldn));
+        // Only compiles if `$fieldt` is the type of the field.
+        let __field_ptr = unsafe { &mut $this.as_mut().get_unchecked_mut().$fieldn as *mut $fieldt };
+        let __uninit_ptr = unsafe { core::pin::Pin::new_unchecked($crate::transmute_maybe_uninit(&mut *__field_ptr)) };
+        $crate::__assert_init(<$fieldt>::$methodn(__uninit_ptr, $($($arg),+)?), __field_ptr);
+    }};
+    ($fieldt:ty: $methodn:ident($this:ident.$fieldn:ident $(, $($arg:expr),+)? $(,)?)) => {{
+        $crate::__trace_init(&*$this, stringify!($fieldn));
+        let __field_ptr = unsafe {
+            &mut $this.as_mut().get_unchecked_mut().$fieldn as *mut $crate::PinOption<$fieldt>
+        };
+        $crate::__assert_none!(__field_ptr, $fieldn);
+        let __uninit_ptr = unsafe { core::pin::Pin::new_unchecked(&mut *__field_ptr).uninit_ptr() };
+        let __value_ptr = __uninit_ptr.as_ptr();
+        $crate::__assert_init(<$fieldt>::$methodn(__uninit_ptr, $($($arg),+)?), __value_ptr);
+        unsafe { core::pin::Pin::new_unchecked(&mut *__field_ptr).assume_some() };
+    }};
     ($this:ident: |$($srcfield:ident),+ => $dstfield:ident| $fieldv:expr) => {{
         let __this_ptr = unsafe { $this.as_mut().get_unchecked_mut() as *mut Self };
         $(let $srcfield = unsafe { &mut (*__this_ptr).$srcfield };)+
         let __dst_ptr = unsafe { &mut (*__this_ptr).$dstfield };
         __dst_ptr.replace($fieldv)
     }};
+    ($this:ident: |$(*$kind:tt $srcfield:ident),+ => $dstfield:ident| $fieldv:expr) => {{
+        let __this_ptr = unsafe { $this.as_mut().get_unchecked_mut() as *mut Self };
+        $(let $srcfield = unsafe { core::ptr::addr_of_mut!((*__this_ptr).$srcfield) } as *$kind _;)+
+        let __dst_ptr = unsafe { &mut (*__this_ptr).$dstfield };
+        __dst_ptr.replace($fieldv)
+    }};
+    ($($rest:tt)*) => {
+        compile_error!(
+            "expected an initializer of a field of `self`, e.g. `pin_field_init!(Inner: init(self.inner))`, \
+             or a self-reference, e.g. `pin_field_init!(self: |buf => view| View::new(buf))`"
+        );
+    };
+}
+/// Panics in debug builds of the user crate if the `PinOption` field is
+/// already `Some`. Used by `pin_field_init!`.
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __assert_none {
+    ($field_ptr:ident, $fieldn:ident) => {
+        debug_assert!(
+            unsafe { &*$field_ptr }.is_none(),
+            "`{}` is already initialized, its value would be leaked",
+            stringify!($fieldn),
+        );
+    };
+}
+/// Runs `pin_field_init!` only if the condition holds, e.g.
+/// `pin_field_init_if!(config.cache => Cache: init(self.cache))`. Otherwise
+/// nothing is written, so a `PinOption` field stays `None`, which it also
+/// stays if the initializer panics.
+#[macro_export]
+macro_rules! pin_field_init_if {
+    ($cond:expr => $($init:tt)+) => {
+        if $cond {
+            $crate::pin_field_init!($($init)+);
+        }
+    };
+}
+/// Assigns an `Option<F>` field of a pinned struct, where `F: Unpin`, e.g.
+/// `pin_field_set!(self.len = self.as_ref().buf().len())`, returning the
+/// previous value like `Option::replace`. The value is evaluated before the
+/// field is borrowed, so it may read the sibling fields through getters.
+#[macro_export]
+macro_rules! pin_field_set {
+    ($this:ident.$fieldn:ident = $fieldv:expr) => {{
+        let __value = $fieldv;
+        $crate::__replace_unpin(unsafe { &mut $this.as_mut().get_unchecked_mut().$fieldn }, __value)
+    }};
+}
+/// Swaps the contents of two pinned values of the same type in place, e.g.
+/// `pin_swap!(front, back)` for double-buffered scratch state, where the
+/// operands are `Pin<&mut T>` (reborrowed by `as_mut`). Neither value is
+/// moved out of its storage, but the bytes are exchanged, so the type must
+/// implement the unsafe [`PinSwap`] trait, which describes how the
+/// self-references are fixed up after the swap.
+/// ```
+/// # use std::{marker::PhantomPinned, pin::{pin, Pin}, ptr};
+/// # use pin_macros::{pin_swap, PinSwap};
+/// // No self-references, so the bytes may simply be swapped.
+/// struct Scratch {
+///     sum: u64,
+///     marker: PhantomPinned,
+/// }
+/// unsafe impl PinSwap for Scratch {}
+///
+/// let mut front = pin!(Scratch { sum: 1, marker: PhantomPinned });
+/// let mut back = pin!(Scratch { sum: 2, marker: PhantomPinned });
+/// pin_swap!(front, back);
+/// assert_eq!((front.sum, back.sum), (2, 1));
+///
+/// // The cursor points into the own buffer, so it is rebased after the swap.
+/// struct Reader {
+///     buf: [u8; 4],
+///     cursor: *const u8,
+///     marker: PhantomPinned,
+/// }
+/// unsafe impl PinSwap for Reader {
+///     fn fix_up(self: Pin<&mut Self>, old: *const Self) {
+///         let this = unsafe { self.get_unchecked_mut() };
+///         let offset = this.cursor as usize - unsafe { ptr::addr_of!((*old).buf) } as usize;
+///         this.cursor = this.buf[offset..].as_ptr();
+///     }
+/// }
+/// impl Reader {
+///     fn load(self: Pin<&mut Self>, buf: [u8; 4], offset: usize) {
+///         let this = unsafe { self.get_unchecked_mut() };
+///         this.buf = buf;
+///         this.cursor = this.buf[offset..].as_ptr();
+///     }
+///     fn peek(&self) -> u8 {
+///         unsafe { *self.cursor }
+///     }
+/// }
+///
+/// let empty = || Reader { buf: [0; 4], cursor: ptr::null(), marker: PhantomPinned };
+/// let (mut a, mut b) = (pin!(empty()), pin!(empty()));
+/// a.as_mut().load(*b"abcd", 1);
+/// b.as_mut().load(*b"wxyz", 3);
+/// pin_swap!(a, b);
+/// assert_eq!((a.peek(), b.peek()), (b'z', b'b'));
+/// assert!(ptr::eq(a.cursor, &a.buf[3]));
+/// ```
+#[macro_export]
+macro_rules! pin_swap {
+    ($a:expr, $b:expr $(,)?) => {
+        $crate::__pin_swap($a.as_mut(), $b.as_mut())
+    };
+}
+/// Copies a slice of `Copy` elements into an array or a slice field of a
+/// pinned struct at once, e.g. `pin_field_copy_slice!(self.buf <= &packet)`,
+/// evaluating to `Result<(), SliceLenError>`. It is `Err` if the lengths
+/// differ, and the field is left untouched then. The source is evaluated
+/// before the field is borrowed.
+/// ```
+/// # use std::{marker::PhantomPinned, pin::pin};
+/// # use pin_macros::{pin_field_copy_slice, SliceLenError};
+/// struct Frame {
+///     buf: [u8; 4],
+///     marker: PhantomPinned,
+/// }
+///
+/// let mut frame = pin!(Frame { buf: [0; 4], marker: PhantomPinned });
+/// assert_eq!(pin_field_copy_slice!(frame.buf <= b"ping"), Ok(()));
+/// assert_eq!(frame.buf, *b"ping");
+/// let error = pin_field_copy_slice!(frame.buf <= b"pong!");
+/// assert_eq!(error, Err(SliceLenError { expected: 4, actual: 5 }));
+/// assert_eq!(frame.buf, *b"ping");
+/// ```
+#[macro_export]
+macro_rules! pin_field_copy_slice {
+    ($this:ident.$fieldn:ident <= $src:expr) => {{
+        let __src = $src;
+        $crate::__copy_slice(unsafe { &mut $this.as_mut().get_unchecked_mut().$fieldn[..] }, &__src[..])
+    }};
+}
+/// Initializes several [`PinOption`] fields in sequence, like the 1st rule of
+/// `pin_field_init!`, e.g. `pin_fields_init!(self => a: A = init(), b: B =
+/// init(1))`. If an initializer panics, the fields initialized before it are
+/// dropped in reverse order and left `None`. Attributes before an entry, e.g.
+/// `#[cfg(feature = "metrics")]`, apply to its initialization, so the fields
+/// absent in a configuration are skipped.
+#[macro_export]
+macro_rules! pin_fields_init {
+    ($this:ident => $($(#[$attr:meta])* $fieldn:ident: $fieldt:ty = $methodn:ident($($arg:expr),* $(,)?)),+ $(,)?) => {{
+        let __armed = core::cell::Cell::new(true);
+        $(
+            $(#[$attr])*
+            $crate::pin_field_init!($fieldt: $methodn($this.$fieldn, $($arg),*));
+            $(#[$attr])*
+            let __guard = unsafe {
+                $crate::__ClearGuard::new(
+                    &__armed,
+                    &mut $this.as_mut().get_unchecked_mut().$fieldn as *mut $crate::PinOption<$fieldt>,
+                )
+            };
+        )+
+        __armed.set(false);
+    }};
+}
+/// Re-initializes [`PinOption`] field of owned immovable type in place, like
+/// the 1st rule of `pin_field_init!`, but the old value is dropped first, if
+/// any. If the initializer panics, the field is left `None`.
+#[macro_export]
+macro_rules! pin_field_reinit {
+    ($fieldt:ty: $methodn:ident($this:ident.$fieldn:ident $(, $($arg:expr),+)? $(,)?)) => {{
+        let __field_ptr = unsafe {
+            &mut $this.as_mut().get_unchecked_mut().$fieldn as *mut $crate::PinOption<$fieldt>
+        };
+        unsafe { core::pin::Pin::new_unchecked(&mut *__field_ptr) }.clear();
+        $crate::pin_field_init!($fieldt: $methodn($this.$fieldn $(, $($arg),+)?));
+    }};
 }
 /// Defines a `Pin<&mut F>` getter, where `F` — field type. Use on owned
-/// immovable values only.
+/// immovable values only. Attributes and visibility before the field name
+/// are forwarded to the getter, while a `const` after them makes it a
+/// `const fn` (Rust 1.84 or newer), e.g. `field_pin!(#[inline] pub buf: Buf)`.
+/// Generic parameters may follow the name, the first lifetime being the one
+/// of the `self` borrow, e.g. `field_pin!(view<'b>: View<'b>)`. A field of a
+/// tuple struct is selected by its index after an arrow, the name being the
+/// getter's only, e.g. `field_pin!(inner -> 0: Inner)`. Similarly, a named
+/// field may be exposed under another name, e.g.
+/// `field_pin!(pub buffer_mut from buf: Buf)`.
+/// ```
+/// # use std::{marker::PhantomPinned, pin::{pin, Pin}};
+/// # use pin_macros::{field_pin, field_unpin};
+/// struct Inner(u32, PhantomPinned);
+/// struct Wrapper(Inner, u32);
+/// impl Wrapper {
+///     field_pin!(inner -> 0: Inner);
+///     field_unpin!(count -> 1: u32);
+/// }
+///
+/// let mut wrapper = pin!(Wrapper(Inner(1, PhantomPinned), 2));
+/// let _: Pin<&mut Inner> = wrapper.as_mut().inner();
+/// *wrapper.as_mut().count() += 1;
+/// assert_eq!(wrapper.1, 3);
+/// ```
 #[macro_export]
 macro_rules! field_pin {
-    ($name:ident: $type:ty) => {
-        fn $name(self: std::pin::Pin<&mut Self>) -> std::pin::Pin<&mut $type> {
-            unsafe { self.map_unchecked_mut(|this| &mut this.$name) }
+    ($(#[$attr:meta])* $v:vis const $name:ident$(<$a:lifetime $(, $gk:tt $($gn:ident)? $(: $($gl:lifetime)? $($gt:path)?)?)*>)? $(-> $index:tt)? $(from $fieldn:ident)?: $type:ty) => {
+        $crate::field_pin!(@emit [$(#[$attr])* $v const] $name[$($a $(, $gk $($gn)? $(: $($gl)? $($gt)?)?)*)?]($name $(, $index)? $(, $fieldn)?): $type);
+    };
+    ($(#[$attr:meta])* $v:vis $name:ident$(<$a:lifetime $(, $gk:tt $($gn:ident)? $(: $($gl:lifetime)? $($gt:path)?)?)*>)? $(-> $index:tt)? $(from $fieldn:ident)?: $type:ty) => {
+        $crate::field_pin!(@emit [$(#[$attr])* $v] $name[$($a $(, $gk $($gn)? $(: $($gl)? $($gt)?)?)*)?]($name $(, $index)? $(, $fieldn)?): $type);
+    };
+    (@emit $qual:tt $name:ident $gen:tt ($_default:ident, $field:tt): $type:ty) => {
+        $crate::field_pin!(@emit $qual $name $gen ($field): $type);
+    };
+    (@emit [$($qual:tt)*] $name:ident[$($a:lifetime $(, $($gen:tt)*)?)?]($field:tt): $type:ty) => {
+        $($qual)* fn $name<$($a $(, $($gen)*)?)?>(self: core::pin::Pin<&$($a)? mut Self>) -> core::pin::Pin<&mut $type> {
+            unsafe { core::pin::Pin::new_unchecked(&mut self.get_unchecked_mut().$field) }
         }
-    }
+    };
+    ($($rest:tt)*) => {
+        compile_error!("expected a field name and its type, e.g. `field_pin!(inner: Inner)`");
+    };
+}
+/// Defines a `Pin<&F>` getter, where `F` — field type. Use on owned immovable
+/// values only. Accepts attributes, visibility and `const` like `field_pin!`.
+#[macro_export]
+macro_rules! field_pin_ref {
+    ($(#[$attr:meta])* $v:vis const $name:ident$(<$a:lifetime $(, $gk:tt $($gn:ident)? $(: $($gl:lifetime)? $($gt:path)?)?)*>)? $(-> $index:tt)? $(from $fieldn:ident)?: $type:ty) => {
+        $crate::field_pin_ref!(@emit [$(#[$attr])* $v const] $name[$($a $(, $gk $($gn)? $(: $($gl)? $($gt)?)?)*)?]($name $(, $index)? $(, $fieldn)?): $type);
+    };
+    ($(#[$attr:meta])* $v:vis $name:ident$(<$a:lifetime $(, $gk:tt $($gn:ident)? $(: $($gl:lifetime)? $($gt:path)?)?)*>)? $(-> $index:tt)? $(from $fieldn:ident)?: $type:ty) => {
+        $crate::field_pin_ref!(@emit [$(#[$attr])* $v] $name[$($a $(, $gk $($gn)? $(: $($gl)? $($gt)?)?)*)?]($name $(, $index)? $(, $fieldn)?): $type);
+    };
+    (@emit $qual:tt $name:ident $gen:tt ($_default:ident, $field:tt): $type:ty) => {
+        $crate::field_pin_ref!(@emit $qual $name $gen ($field): $type);
+    };
+    (@emit [$($qual:tt)*] $name:ident[$($a:lifetime $(, $($gen:tt)*)?)?]($field:tt): $type:ty) => {
+        $($qual)* fn $name<$($a $(, $($gen)*)?)?>(self: core::pin::Pin<&$($a)? Self>) -> core::pin::Pin<&$type> {
+            unsafe { core::pin::Pin::new_unchecked(&self.get_ref().$field) }
+        }
+    };
+    ($($rest:tt)*) => {
+        compile_error!("expected a field name and its type, e.g. `field_pin_ref!(inner: Inner)`");
+    };
+}
+/// Defines a `Pin<&mut F>` getter of a nested field, where `F` — type of the
+/// field at the end of the path, e.g. `field_pin_path!(buf: inner.buf => Buf)`.
+/// Accepts attributes and visibility like `field_pin!`. Every field along the
+/// path must be an owned immovable value.
+/// ```
+/// # use std::{marker::PhantomPinned, pin::pin};
+/// # use pin_macros::field_pin_path;
+/// struct Buffer(u32, PhantomPinned);
+/// struct Inner {
+///     buffer: Buffer,
+/// }
+/// struct Outer {
+///     inner: (Inner,),
+/// }
+/// impl Outer {
+///     field_pin_path!(buffer: inner.0.buffer => Buffer);
+/// }
+///
+/// let mut outer = pin!(Outer { inner: (Inner { buffer: Buffer(1, PhantomPinned) },) });
+/// assert_eq!(outer.as_mut().buffer().0, 1);
+/// ```
+#[macro_export]
+macro_rules! field_pin_path {
+    ($(#[$attr:meta])* $v:vis $name:ident: $($field:tt).+ => $type:ty) => {
+        $(#[$attr])*
+        $v fn $name(self: core::pin::Pin<&mut Self>) -> core::pin::Pin<&mut $type> {
+            unsafe { core::pin::Pin::new_unchecked(&mut self.get_unchecked_mut()$(.$field)+) }
+        }
+    };
+}
+/// Defines a `Pin<&F>` getter of a nested field like `field_pin_path!`, taking
+/// `self: Pin<&Self>`, e.g. `field_pin_ref_path!(buf: inner.buf => Buf)`.
+/// Together with `field_pin_ref!` it lets a read-only traversal of an
+/// immovable tree go without `&mut`, so the queries may take `Pin<&Self>`
+/// and run concurrently.
+/// ```
+/// # use std::{marker::PhantomPinned, pin::{pin, Pin}, thread};
+/// # use pin_macros::{field_pin_ref, field_pin_ref_path};
+/// struct Leaf(u32, PhantomPinned);
+/// struct Branch {
+///     leaves: (Leaf, Leaf),
+/// }
+/// impl Branch {
+///     field_pin_ref_path!(first: leaves.0 => Leaf);
+///     field_pin_ref_path!(second: leaves.1 => Leaf);
+/// }
+/// struct Tree {
+///     branch: Branch,
+///     marker: PhantomPinned,
+/// }
+/// impl Tree {
+///     field_pin_ref!(branch: Branch);
+///     field_pin_ref_path!(first_leaf: branch.leaves.0 => Leaf);
+///
+///     fn sum(self: Pin<&Self>) -> u32 {
+///         let branch = self.branch();
+///         branch.first().0 + branch.second().0
+///     }
+/// }
+///
+/// let tree = pin!(Tree {
+///     branch: Branch { leaves: (Leaf(1, PhantomPinned), Leaf(2, PhantomPinned)) },
+///     marker: PhantomPinned,
+/// });
+/// let tree = tree.into_ref();
+/// thread::scope(|scope| {
+///     scope.spawn(|| assert_eq!(tree.sum(), 3));
+///     scope.spawn(|| assert_eq!(tree.first_leaf().0, 1));
+/// });
+/// ```
+#[macro_export]
+macro_rules! field_pin_ref_path {
+    ($(#[$attr:meta])* $v:vis $name:ident: $($field:tt).+ => $type:ty) => {
+        $(#[$attr])*
+        $v fn $name(self: core::pin::Pin<&Self>) -> core::pin::Pin<&$type> {
+            unsafe { core::pin::Pin::new_unchecked(&self.get_ref()$(.$field)+) }
+        }
+    };
+}
+/// Defines a `Pin<&mut [E]>` getter of an array or a slice field, where `E` —
+/// element type, e.g. `field_pin_slice!(nodes: Node)`. Use on owned immovable
+/// elements only. Accepts attributes and visibility like `field_pin!`. The
+/// elements may be iterated through [`PinSliceIterMut`].
+#[macro_export]
+macro_rules! field_pin_slice {
+    ($(#[$attr:meta])* $v:vis $name:ident: $elemt:ty) => {
+        $(#[$attr])*
+        $v fn $name(self: core::pin::Pin<&mut Self>) -> core::pin::Pin<&mut [$elemt]> {
+            unsafe { core::pin::Pin::new_unchecked(&mut self.get_unchecked_mut().$name[..]) }
+        }
+    };
+}
+/// Defines `as_pin_ref`, obtaining `Pin<&I>` from `Pin<&Self>`, and
+/// `as_pin_mut`, obtaining `Pin<&mut I>` from `Pin<&mut Self>`, where `I` —
+/// type of the primary field of a wrapper, e.g. `pin_inner!(Inner via inner)`.
+/// Accepts attributes and visibility like `field_pin!`. The field must be an
+/// owned immovable value.
+/// ```
+/// # use std::{marker::PhantomPinned, pin::pin};
+/// # use pin_macros::pin_inner;
+/// struct Timer {
+///     ticks: u32,
+///     marker: PhantomPinned,
+/// }
+/// struct Traced {
+///     timer: Timer,
+/// }
+/// impl Traced {
+///     pin_inner!(pub Timer via timer);
+/// }
+/// struct Limited {
+///     traced: Traced,
+///     limit: u32,
+/// }
+/// impl Limited {
+///     pin_inner!(pub Traced via traced);
+/// }
+///
+/// let mut limited = pin!(Limited { traced: Traced { timer: Timer { ticks: 3, marker: PhantomPinned } }, limit: 5 });
+/// let timer = limited.as_mut().as_pin_mut().as_pin_mut();
+/// assert_eq!(timer.ticks, 3);
+/// assert_eq!(limited.as_ref().as_pin_ref().as_pin_ref().ticks, 3);
+/// ```
+#[macro_export]
+macro_rules! pin_inner {
+    // A type can't be followed by `via`, so it is collected token by token.
+    (@type [$($qual:tt)*] [$($type:tt)+] via $fieldn:tt) => {
+        $($qual)* fn as_pin_ref(self: core::pin::Pin<&Self>) -> core::pin::Pin<&$($type)+> {
+            unsafe { core::pin::Pin::new_unchecked(&self.get_ref().$fieldn) }
+        }
+        $($qual)* fn as_pin_mut(self: core::pin::Pin<&mut Self>) -> core::pin::Pin<&mut $($type)+> {
+            unsafe { core::pin::Pin::new_unchecked(&mut self.get_unchecked_mut().$fieldn) }
+        }
+    };
+    (@type $qual:tt [$($type:tt)*] $t:tt $($rest:tt)+) => {
+        $crate::pin_inner!(@type $qual [$($type)* $t] $($rest)+);
+    };
+    ($(#[$attr:meta])* $v:vis $head:ident $($rest:tt)+) => {
+        $crate::pin_inner!(@type [$(#[$attr])* $v] [$head] $($rest)+);
+    };
+}
+/// Defines `into_inner_pin`, converting `Pin<&mut Self>` to `Pin<&mut I>`, and
+/// `from_inner_pin`, converting it back, where `I` — the type `Self` wraps.
+/// `Self` must be `#[repr(transparent)]` over `I`, so that the whole value is
+/// reinterpreted instead of crossing a field boundary. Accepts attributes and
+/// visibility like `field_pin!`, e.g. `field_pin_transparent!(pub Inner)`.
+/// The size and the alignment of the two types are asserted to match at
+/// compile time.
+/// ```
+/// # use std::{marker::PhantomPinned, mem::MaybeUninit, pin::{pin, Pin}, ptr};
+/// # use pin_macros::field_pin_transparent;
+/// struct Node {
+///     this: *const Node,
+///     marker: PhantomPinned,
+/// }
+/// #[repr(transparent)]
+/// struct Handle(Node);
+/// impl Handle {
+///     field_pin_transparent!(Node);
+/// }
+///
+/// let mut node = pin!(Node { this: ptr::null(), marker: PhantomPinned });
+/// unsafe { node.as_mut().get_unchecked_mut().this = &*node };
+/// let handle = Handle::from_inner_pin(node);
+/// let node = handle.into_inner_pin();
+/// assert!(ptr::eq(node.this, &*node));
+/// ```
+#[macro_export]
+macro_rules! field_pin_transparent {
+    ($(#[$attr:meta])* $v:vis $type:ty) => {
+        $(#[$attr])*
+        $v fn into_inner_pin(self: core::pin::Pin<&mut Self>) -> core::pin::Pin<&mut $type> {
+            #[allow(clippy::let_unit_value)]
+            let () = $crate::__TransparentLayout::<Self, $type>::ASSERT;
+            unsafe { core::pin::Pin::new_unchecked(&mut *(self.get_unchecked_mut() as *mut Self as *mut $type)) }
+        }
+        $(#[$attr])*
+        $v fn from_inner_pin(inner: core::pin::Pin<&mut $type>) -> core::pin::Pin<&mut Self> {
+            #[allow(clippy::let_unit_value)]
+            let () = $crate::__TransparentLayout::<Self, $type>::ASSERT;
+            unsafe { core::pin::Pin::new_unchecked(&mut *(inner.get_unchecked_mut() as *mut $type as *mut Self)) }
+        }
+    };
+}
+/// Defines a generic `Option<Pin<&mut C>>` getter of a type-erased field,
+/// returning `None` unless `C` is the concrete type of the value, where the
+/// field type is `dyn Any` (the last field of an unsized struct) or a pinned
+/// pointer to it, e.g. `field_pin_downcast!(plugin: Pin<Box<dyn Any>>)`.
+/// `Send` and `Sync` bounds on the trait object are accepted too. Use on owned
+/// immovable values only. Accepts attributes, visibility and `from` like
+/// `field_pin!`.
+/// ```
+/// # use std::{any::Any, marker::PhantomPinned, pin::{pin, Pin}};
+/// # use pin_macros::field_pin_downcast;
+/// struct Counter(u32, PhantomPinned);
+/// struct Node {
+///     plugin: Pin<Box<dyn Any>>,
+/// }
+/// impl Node {
+///     field_pin_downcast!(plugin: Pin<Box<dyn Any>>);
+/// }
+///
+/// let mut node = pin!(Node { plugin: Box::pin(Counter(1, PhantomPinned)) });
+/// let counter: Pin<&mut Counter> = node.as_mut().plugin().unwrap();
+/// assert_eq!(counter.0, 1);
+/// assert!(node.as_mut().plugin::<String>().is_none());
+/// ```
+#[macro_export]
+macro_rules! field_pin_downcast {
+    ($(#[$attr:meta])* $v:vis $name:ident from $fieldn:ident: $type:ty) => {
+        $crate::field_pin_downcast!(@emit [$(#[$attr])* $v] $name($fieldn): $type);
+    };
+    ($(#[$attr:meta])* $v:vis $name:ident: $type:ty) => {
+        $crate::field_pin_downcast!(@emit [$(#[$attr])* $v] $name($name): $type);
+    };
+    (@emit [$($qual:tt)*] $name:ident($field:ident): $type:ty) => {
+        $($qual)* fn $name<C: core::any::Any>(self: core::pin::Pin<&mut Self>) -> core::option::Option<core::pin::Pin<&mut C>> {
+            let field: core::pin::Pin<&mut $type> = unsafe { core::pin::Pin::new_unchecked(&mut self.get_unchecked_mut().$field) };
+            $crate::downcast_pin(<$type as $crate::__PinAny>::__pin_any(field))
+        }
+    };
+}
+/// Asserts a `repr(transparent)` wrapper to have the layout of the wrapped
+/// type once `ASSERT` is used. Used by `field_pin_transparent!`.
+#[doc(hidden)]
+pub struct __TransparentLayout<W, T>(core::marker::PhantomData<(W, T)>);
+impl<W, T> __TransparentLayout<W, T> {
+    pub const ASSERT: () = assert!(
+        mem::size_of::<W>() == mem::size_of::<T>() && mem::align_of::<W>() == mem::align_of::<T>(),
+        "the `repr(transparent)` wrapper layout differs from the wrapped type",
+    );
+}
+/// Defines an `Option<Pin<&mut P>>` getter, where `P` — payload type of a
+/// single-field tuple variant of `Self`. Returns `None` unless `Self` is in
+/// that variant. Use on owned immovable payloads only.
+/// ```
+/// # use std::{marker::PhantomPinned, pin::pin};
+/// # use pin_macros::field_pin_variant;
+/// struct Payload(u32, PhantomPinned);
+/// enum State {
+///     Idle,
+///     Running(Payload),
+/// }
+/// impl State {
+///     field_pin_variant!(running: State::Running => Payload);
+/// }
+///
+/// let mut state = pin!(State::Running(Payload(1, PhantomPinned)));
+/// assert_eq!(state.as_mut().running().map(|payload| payload.0), Some(1));
+/// let mut state = pin!(State::Idle);
+/// assert!(state.as_mut().running().is_none());
+/// ```
+#[macro_export]
+macro_rules! field_pin_variant {
+    ($(#[$attr:meta])* $v:vis $name:ident: $variant:path => $type:ty) => {
+        $(#[$attr])*
+        $v fn $name(self: core::pin::Pin<&mut Self>) -> core::option::Option<core::pin::Pin<&mut $type>> {
+            match unsafe { self.get_unchecked_mut() } {
+                $variant(__payload) => core::option::Option::Some(unsafe { core::pin::Pin::new_unchecked(__payload) }),
+                #[allow(unreachable_patterns)]
+                _ => core::option::Option::None,
+            }
+        }
+    };
+}
+/// Defines both `field_pin!` and `field_pin_ref!` getters of the same field.
+/// Since `macro_rules!` cannot concatenate identifiers, the name of the
+/// `Pin<&F>` getter follows the field name, e.g.
+/// `field_pin_both!(inner, inner_ref: Inner)`.
+#[macro_export]
+macro_rules! field_pin_both {
+    ($(#[$attr:meta])* $v:vis $name:ident, $ref_name:ident: $type:ty) => {
+        $crate::field_pin!(@emit [$(#[$attr])* $v] $name[]($name): $type);
+        $crate::field_pin_ref!(@emit [$(#[$attr])* $v] $ref_name[]($name): $type);
+    };
 }
-/// Defines a `&mut F` getter, where `F` — field type.
+/// Defines a `&mut F` getter, where `F` — field type. Accepts attributes,
+/// visibility and `const` like `field_pin!`.
+///
+/// The field is obtained through `Pin::get_mut`, so `F` must be `Unpin`, and
+/// the getter fails to compile for an immovable field, which could be moved
+/// out of the reference otherwise. A generic field type must be bound by
+/// `Unpin` then.
+/// ```compile_fail
+/// # use std::marker::PhantomPinned;
+/// # use pin_macros::field_unpin;
+/// struct Outer {
+///     inner: PhantomPinned,
+/// }
+/// impl Outer {
+///     field_unpin!(inner: PhantomPinned);
+/// }
+/// ```
 #[macro_export]
 macro_rules! field_unpin {
-    ($name:ident: $type:ty) => {
-        fn $name(self: std::pin::Pin<&mut Self>) -> &mut $type {
-            unsafe { self.map_unchecked_mut(|this| &mut this.$name).get_mut() }
+    ($(#[$attr:meta])* $v:vis const $name:ident$(<$a:lifetime $(, $gk:tt $($gn:ident)? $(: $($gl:lifetime)? $($gt:path)?)?)*>)? $(-> $index:tt)? $(from $fieldn:ident)?: $type:ty) => {
+        $crate::field_unpin!(@emit [$(#[$attr])* $v const] $name[$($a $(, $gk $($gn)? $(: $($gl)? $($gt)?)?)*)?]($name $(, $index)? $(, $fieldn)?): $type);
+    };
+    ($(#[$attr:meta])* $v:vis $name:ident$(<$a:lifetime $(, $gk:tt $($gn:ident)? $(: $($gl:lifetime)? $($gt:path)?)?)*>)? $(-> $index:tt)? $(from $fieldn:ident)?: $type:ty) => {
+        $crate::field_unpin!(@emit [$(#[$attr])* $v] $name[$($a $(, $gk $($gn)? $(: $($gl)? $($gt)?)?)*)?]($name $(, $index)? $(, $fieldn)?): $type);
+    };
+    (@emit $qual:tt $name:ident $gen:tt ($_default:ident, $field:tt): $type:ty) => {
+        $crate::field_unpin!(@emit $qual $name $gen ($field): $type);
+    };
+    (@emit [$($qual:tt)*] $name:ident[$($a:lifetime $(, $($gen:tt)*)?)?]($field:tt): $type:ty) => {
+        $($qual)* fn $name<$($a $(, $($gen)*)?)?>(self: core::pin::Pin<&$($a)? mut Self>) -> &mut $type {
+            unsafe { core::pin::Pin::new_unchecked(&mut self.get_unchecked_mut().$field) }.get_mut()
+        }
+    };
+    ($($rest:tt)*) => {
+        compile_error!("expected a field name and its type, e.g. `field_unpin!(inner: Inner)`");
+    };
+}
+/// Defines a method moving an `Unpin` field out of `self: Pin<&mut Self>` by
+/// `mem::take`, leaving `F::default()` behind, where `F` — field type, e.g.
+/// `field_take!(take_items from items: Vec<u32>)`. Accepts attributes,
+/// visibility, `-> index` and `from field` like `field_pin!`. Like
+/// `field_unpin!`, it fails to compile unless `F` is `Unpin`.
+/// ```
+/// # use std::{marker::PhantomPinned, pin::pin};
+/// # use pin_macros::{field_replace, field_take};
+/// struct Batch {
+///     items: Vec<u32>,
+///     marker: PhantomPinned,
+/// }
+/// impl Batch {
+///     field_take!(take_items from items: Vec<u32>);
+///     field_replace!(replace_items from items: Vec<u32>);
+/// }
+///
+/// let mut batch = pin!(Batch { items: vec![1, 2], marker: PhantomPinned });
+/// assert_eq!(batch.as_mut().take_items(), [1, 2]);
+/// assert!(batch.items.is_empty());
+/// assert!(batch.as_mut().replace_items(vec![3]).is_empty());
+/// assert_eq!(batch.items, [3]);
+/// ```
+///
+/// An immovable field can't be taken, even if it implements `Default`.
+/// ```compile_fail
+/// # use std::marker::PhantomPinned;
+/// # use pin_macros::field_take;
+/// struct Outer {
+///     marker: PhantomPinned,
+/// }
+/// impl Outer {
+///     field_take!(take_marker from marker: PhantomPinned);
+/// }
+/// ```
+#[macro_export]
+macro_rules! field_take {
+    ($(#[$attr:meta])* $v:vis $name:ident $(-> $index:tt)? $(from $fieldn:ident)?: $type:ty) => {
+        $crate::field_take!(@emit [$(#[$attr])* $v] $name($name $(, $index)? $(, $fieldn)?): $type);
+    };
+    (@emit $qual:tt $name:ident ($_default:ident, $field:tt): $type:ty) => {
+        $crate::field_take!(@emit $qual $name ($field): $type);
+    };
+    (@emit [$($qual:tt)*] $name:ident($field:tt): $type:ty) => {
+        $($qual)* fn $name(self: core::pin::Pin<&mut Self>) -> $type {
+            core::mem::take(unsafe { core::pin::Pin::new_unchecked(&mut self.get_unchecked_mut().$field) }.get_mut())
+        }
+    };
+    ($($rest:tt)*) => {
+        compile_error!("expected a method name and the field type, e.g. `field_take!(take_items from items: Vec<u32>)`");
+    };
+}
+/// Defines a method swapping a new value into an `Unpin` field of `self:
+/// Pin<&mut Self>` by `mem::replace`, returning the old one, e.g.
+/// `field_replace!(replace_items from items: Vec<u32>)` (see `field_take!`).
+#[macro_export]
+macro_rules! field_replace {
+    ($(#[$attr:meta])* $v:vis $name:ident $(-> $index:tt)? $(from $fieldn:ident)?: $type:ty) => {
+        $crate::field_replace!(@emit [$(#[$attr])* $v] $name($name $(, $index)? $(, $fieldn)?): $type);
+    };
+    (@emit $qual:tt $name:ident ($_default:ident, $field:tt): $type:ty) => {
+        $crate::field_replace!(@emit $qual $name ($field): $type);
+    };
+    (@emit [$($qual:tt)*] $name:ident($field:tt): $type:ty) => {
+        $($qual)* fn $name(self: core::pin::Pin<&mut Self>, value: $type) -> $type {
+            core::mem::replace(unsafe { core::pin::Pin::new_unchecked(&mut self.get_unchecked_mut().$field) }.get_mut(), value)
+        }
+    };
+    ($($rest:tt)*) => {
+        compile_error!("expected a method name and the field type, e.g. `field_replace!(replace_items from items: Vec<u32>)`");
+    };
+}
+/// Projects a field of owned immovable future and polls it, e.g.
+/// `poll_field!(self.inner, cx)` in `Future::poll`, evaluating to the
+/// `Poll`. Like `field_pin!`, the field must not be moved out of `self`.
+/// ```
+/// # use std::{future::Future, marker::PhantomPinned, pin::{pin, Pin}, task::{Context, Poll, Waker}};
+/// # use pin_macros::poll_field;
+/// // Completes on the given poll.
+/// struct Delay {
+///     polls: u32,
+///     marker: PhantomPinned,
+/// }
+/// impl Future for Delay {
+///     type Output = ();
+///
+///     fn poll(self: Pin<&mut Self>, cx: &mut Context) -> Poll<()> {
+///         let this = unsafe { self.get_unchecked_mut() };
+///         this.polls = this.polls.saturating_sub(1);
+///         if this.polls == 0 { Poll::Ready(()) } else { cx.waker().wake_by_ref(); Poll::Pending }
+///     }
+/// }
+/// struct Timeout {
+///     inner: Delay,
+///     limit: Delay,
+/// }
+/// impl Future for Timeout {
+///     type Output = bool;
+///
+///     fn poll(mut self: Pin<&mut Self>, cx: &mut Context) -> Poll<bool> {
+///         if poll_field!(self.inner, cx).is_ready() {
+///             return Poll::Ready(true);
+///         }
+///         poll_field!(self.limit, cx).map(|()| false)
+///     }
+/// }
+///
+/// let mut cx = Context::from_waker(Waker::noop());
+/// let delay = |polls| Delay { polls, marker: PhantomPinned };
+/// let mut timeout = pin!(Timeout { inner: delay(3), limit: delay(5) });
+/// assert!(timeout.as_mut().poll(&mut cx).is_pending());
+/// assert!(timeout.as_mut().poll(&mut cx).is_pending());
+/// assert_eq!(timeout.as_mut().poll(&mut cx), Poll::Ready(true));
+/// ```
+#[macro_export]
+macro_rules! poll_field {
+    ($this:ident.$fieldn:tt, $cx:expr $(,)?) => {
+        core::future::Future::poll(
+            unsafe { core::pin::Pin::new_unchecked(&mut $this.as_mut().get_unchecked_mut().$fieldn) },
+            $cx,
+        )
+    };
+}
+/// Implements `Future` for an immovable type, whose `poll` gets the pinned
+/// receiver, so it may use the projection getters like `field_pin!`. Only the
+/// names of the parameters and the `Output` type are declared, e.g.
+/// `pin_future_impl!(impl<'a> Read<'a> { fn poll(self, cx) -> usize {..} })`,
+/// and the block evaluates to `Poll<Output>`.
+/// ```
+/// # use std::{future::Future, marker::PhantomPinned, pin::{pin, Pin}, task::{Context, Poll, Waker}};
+/// # use pin_macros::{field_pin, field_unpin, pin_future_impl, pin_init, pin_new};
+/// // Sums its own buffer one element per poll, through a borrow of it.
+/// struct Sum<'a> {
+///     buf: [u32; 3],
+///     rest: &'a [u32],
+///     total: u32,
+///     marker: PhantomPinned,
+/// }
+/// impl<'a> Sum<'a> {
+///     pin_init!(fn init<'a>(this, buf: [u32; 3]) {
+///         this.buf = buf;
+///         this.rest = unsafe { &*core::ptr::addr_of!(this.buf) };
+///         this.total = 0;
+///     });
+///     field_unpin!(rest: &'a [u32]);
+///     field_unpin!(total: u32);
+/// }
+/// pin_future_impl!(impl<'a> Sum<'a> {
+///     fn poll(mut self, cx) -> u32 {
+///         let rest = *self.as_mut().rest();
+///         let Some((first, rest)) = rest.split_first() else {
+///             return Poll::Ready(*self.as_mut().total());
+///         };
+///         *self.as_mut().rest() = rest;
+///         *self.as_mut().total() += first;
+///         cx.waker().wake_by_ref();
+///         Poll::Pending
+///     }
+/// });
+///
+/// pin_new!(mut sum: Sum = init([1, 2, 3]));
+/// let mut cx = Context::from_waker(Waker::noop());
+/// let output = loop {
+///     if let Poll::Ready(output) = sum.as_mut().poll(&mut cx) {
+///         break output;
+///     }
+/// };
+/// assert_eq!(output, 6);
+/// ```
+#[macro_export]
+macro_rules! pin_future_impl {
+    ($(#[$attr:meta])* impl$(<$($gen:lifetime),* $(,)?>)? $name:ident $(<$($arg:lifetime),* $(,)?>)? {
+        fn poll(mut $this:ident, $cx:ident) -> $output:ty $body:block
+    }) => {
+        $crate::pin_future_impl!(@emit [$(#[$attr])*] [$($($gen),*)?] $name [$($($arg),*)?] [mut $this] $cx, $output, $body);
+    };
+    ($(#[$attr:meta])* impl$(<$($gen:lifetime),* $(,)?>)? $name:ident $(<$($arg:lifetime),* $(,)?>)? {
+        fn poll($this:ident, $cx:ident) -> $output:ty $body:block
+    }) => {
+        $crate::pin_future_impl!(@emit [$(#[$attr])*] [$($($gen),*)?] $name [$($($arg),*)?] [$this] $cx, $output, $body);
+    };
+    (@emit [$($attr:tt)*] [$($gen:tt)*] $name:ident [$($arg:tt)*] [$($this:tt)+] $cx:ident, $output:ty, $body:block) => {
+        $($attr)*
+        impl<$($gen)*> core::future::Future for $name<$($arg)*> {
+            type Output = $output;
+
+            fn poll($($this)+: core::pin::Pin<&mut Self>, $cx: &mut core::task::Context<'_>) -> core::task::Poll<$output> {
+                $body
+            }
+        }
+    };
+}
+/// Re-borrows `Pin<&mut Self>` for a single use, so the pinned getters, which
+/// consume their receiver, may be called in sequence on the same pointer.
+/// `pin_reborrow!(self)` is `self.as_mut()`, and a getter chain after the
+/// name is called on the re-borrow, e.g. `pin_reborrow!(self.inner().buf())`.
+/// ```
+/// # use std::{future::Future, marker::PhantomPinned, pin::Pin, task::{Context, Poll, Waker}};
+/// # use pin_macros::{field_pin, field_unpin, pin_reborrow};
+/// struct Ready(u32, PhantomPinned);
+/// impl Future for Ready {
+///     type Output = u32;
+///
+///     fn poll(self: Pin<&mut Self>, _: &mut Context) -> Poll<u32> {
+///         Poll::Ready(self.0)
+///     }
+/// }
+/// struct Sum {
+///     a: Ready,
+///     b: Ready,
+///     polls: u32,
+/// }
+/// impl Sum {
+///     field_pin!(a: Ready);
+///     field_pin!(b: Ready);
+///     field_unpin!(polls: u32);
+/// }
+/// impl Future for Sum {
+///     type Output = u32;
+///
+///     fn poll(mut self: Pin<&mut Self>, cx: &mut Context) -> Poll<u32> {
+///         *pin_reborrow!(self.polls()) += 1;
+///         let a = pin_reborrow!(self.a()).poll(cx);
+///         let b = pin_reborrow!(self).b().poll(cx);
+///         match (a, b) {
+///             (Poll::Ready(a), Poll::Ready(b)) => Poll::Ready(a + b),
+///             _ => Poll::Pending,
+///         }
+///     }
+/// }
+///
+/// let mut sum = Box::pin(Sum { a: Ready(1, PhantomPinned), b: Ready(2, PhantomPinned), polls: 0 });
+/// let mut cx = Context::from_waker(Waker::noop());
+/// assert_eq!(sum.as_mut().poll(&mut cx), Poll::Ready(3));
+/// assert_eq!(sum.polls, 1);
+/// ```
+#[macro_export]
+macro_rules! pin_reborrow {
+    ($this:ident) => {
+        core::pin::Pin::as_mut(&mut $this)
+    };
+    ($this:ident $(. $getter:ident($($arg:expr),* $(,)?))+) => {
+        core::pin::Pin::as_mut(&mut $this)$(.$getter($($arg),*))+
+    };
+}
+/// Defines a fallible `Self::init`-like method, returning
+/// `Result<Pin<&mut Self>, E>`. Same macros as in `pin_init!` are available
+/// inside the block.
+#[macro_export]
+macro_rules! pin_try_init {
+    ($(#[$attr:meta])* $v:vis fn $name:ident<$a:lifetime $(, $gk:tt $($gn:ident)? $(: $($gl:lifetime)? $($gt:path)?)?)*>(
+        $this:ident $(, $($argn:ident: $argt:ty),+)? $(,)?
+    ) -> Result<Self, $err:ty> $($rest:tt)+) => {
+        $crate::pin_init!(@where(($(#[$attr])* #[track_caller] $v fn) $name[$($gk $($gn)? $(: $($gl)? $($gt)?)?),*](
+            $a, $this, $($($argn: $argt),+)?
+        ) -> [core::result::Result<core::pin::Pin<&$a mut Self>, $err>] [Ok]) {} $($rest)+);
+    };
+}
+/// Defines a `&F` getter, where `F` — field type. Accepts attributes,
+/// visibility and `const` like `field_pin!`.
+/// ```
+/// # use std::pin::Pin;
+/// # use pin_macros::field_ref;
+/// struct Config {
+///     limit: u32,
+/// }
+/// impl Config {
+///     field_ref!(const limit: u32);
+/// }
+///
+/// static CONFIG: Config = Config { limit: 8 };
+/// const LIMIT: u32 = *Pin::static_ref(&CONFIG).limit();
+/// assert_eq!(LIMIT, 8);
+/// ```
+#[macro_export]
+macro_rules! field_ref {
+    ($(#[$attr:meta])* $v:vis const $name:ident$(<$a:lifetime $(, $gk:tt $($gn:ident)? $(: $($gl:lifetime)? $($gt:path)?)?)*>)? $(-> $index:tt)? $(from $fieldn:ident)?: $type:ty) => {
+        $crate::field_ref!(@emit [$(#[$attr])* $v const] $name[$($a $(, $gk $($gn)? $(: $($gl)? $($gt)?)?)*)?]($name $(, $index)? $(, $fieldn)?): $type);
+    };
+    ($(#[$attr:meta])* $v:vis $name:ident$(<$a:lifetime $(, $gk:tt $($gn:ident)? $(: $($gl:lifetime)? $($gt:path)?)?)*>)? $(-> $index:tt)? $(from $fieldn:ident)?: $type:ty) => {
+        $crate::field_ref!(@emit [$(#[$attr])* $v] $name[$($a $(, $gk $($gn)? $(: $($gl)? $($gt)?)?)*)?]($name $(, $index)? $(, $fieldn)?): $type);
+    };
+    (@emit $qual:tt $name:ident $gen:tt ($_default:ident, $field:tt): $type:ty) => {
+        $crate::field_ref!(@emit $qual $name $gen ($field): $type);
+    };
+    (@emit [$($qual:tt)*] $name:ident[$($a:lifetime $(, $($gen:tt)*)?)?]($field:tt): $type:ty) => {
+        $($qual)* fn $name<$($a $(, $($gen)*)?)?>(self: core::pin::Pin<&$($a)? Self>) -> &$type {
+            &self.get_ref().$field
+        }
+    };
+}
+/// Defines a `&F` getter of a movable field, the read-only counterpart of
+/// `field_unpin!`. The same as `field_ref!`, named to pair with the mutable
+/// getter.
+/// ```
+/// # use std::{marker::PhantomPinned, pin::pin};
+/// # use pin_macros::field_unpin_ref;
+/// struct Handle {
+///     id: u32,
+///     marker: PhantomPinned,
+/// }
+/// impl Handle {
+///     field_unpin_ref!(pub id: u32);
+/// }
+///
+/// let handle = pin!(Handle { id: 3, marker: PhantomPinned });
+/// assert_eq!(*handle.as_ref().id(), 3);
+/// ```
+#[macro_export]
+macro_rules! field_unpin_ref {
+    ($($getter:tt)+) => {
+        $crate::field_ref!($($getter)+);
+    };
+}
+/// Defines getters for several fields at once, choosing the getter by the
+/// annotation following the field type: `(pin)` for `field_pin!`,
+/// `(pin_ref)` for `field_pin_ref!`, `(unpin)` for `field_unpin!`,
+/// `(unpin_ref)` for `field_unpin_ref!` and `(ref)` for `field_ref!`.
+/// Lifetimes of `Self` are declared after `struct`.
+/// ```
+/// # use std::{marker::PhantomPinned, pin::pin};
+/// # use pin_macros::pin_getters;
+/// struct Foo {
+///     a: PhantomPinned,
+///     b: u32,
+///     c: String,
+/// }
+/// pin_getters! {
+///     struct Foo {
+///         a: PhantomPinned (pin),
+///         b: u32 (unpin),
+///         c: String (ref),
+///     }
+/// }
+///
+/// let mut foo = pin!(Foo { a: PhantomPinned, b: 1, c: String::from("c") });
+/// let _: std::pin::Pin<&mut PhantomPinned> = foo.as_mut().a();
+/// *foo.as_mut().b() += 1;
+/// assert_eq!(*foo.as_mut().b(), 2);
+/// assert_eq!(foo.as_ref().c(), "c");
+/// ```
+#[macro_export]
+macro_rules! pin_getters {
+    (struct<$($gen:lifetime),* $(,)?> $selft:ty { $($fields:tt)* }) => {
+        $crate::pin_getters!(@fields [<$($gen),*> $selft] {} $($fields)*);
+    };
+    (struct $selft:ty { $($fields:tt)* }) => {
+        $crate::pin_getters!(struct<> $selft { $($fields)* });
+    };
+    (@fields [<$($gen:lifetime),*> $selft:ty] {$($getters:tt)*}) => {
+        impl<$($gen),*> $selft {
+            $($getters)*
         }
     };
+    (@fields $head:tt {$($getters:tt)*} $field:ident: $($rest:tt)+) => {
+        $crate::pin_getters!(@type $head {$($getters)*} $field [] $($rest)+);
+    };
+    (@type $head:tt {$($getters:tt)*} $field:ident [$($type:tt)+] (pin) $(, $($rest:tt)*)?) => {
+        $crate::pin_getters!(@fields $head {$($getters)* $crate::field_pin!($field: $($type)+);} $($($rest)*)?);
+    };
+    (@type $head:tt {$($getters:tt)*} $field:ident [$($type:tt)+] (pin_ref) $(, $($rest:tt)*)?) => {
+        $crate::pin_getters!(@fields $head {$($getters)* $crate::field_pin_ref!($field: $($type)+);} $($($rest)*)?);
+    };
+    (@type $head:tt {$($getters:tt)*} $field:ident [$($type:tt)+] (unpin) $(, $($rest:tt)*)?) => {
+        $crate::pin_getters!(@fields $head {$($getters)* $crate::field_unpin!($field: $($type)+);} $($($rest)*)?);
+    };
+    (@type $head:tt {$($getters:tt)*} $field:ident [$($type:tt)+] (ref) $(, $($rest:tt)*)?) => {
+        $crate::pin_getters!(@fields $head {$($getters)* $crate::field_ref!($field: $($type)+);} $($($rest)*)?);
+    };
+    (@type $head:tt {$($getters:tt)*} $field:ident [$($type:tt)+] (unpin_ref) $(, $($rest:tt)*)?) => {
+        $crate::pin_getters!(@fields $head {$($getters)* $crate::field_unpin_ref!($field: $($type)+);} $($($rest)*)?);
+    };
+    (@type $head:tt {$($getters:tt)*} $field:ident [$($type:tt)*] $t:tt $($rest:tt)*) => {
+        $crate::pin_getters!(@type $head {$($getters)*} $field [$($type)* $t] $($rest)*);
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use core::mem::{self, MaybeUninit};
+
+    use super::PinOption;
+
+    fn assert_same_layout<T>() {
+        assert_eq!(mem::size_of::<T>(), mem::size_of::<MaybeUninit<T>>());
+        assert_eq!(mem::align_of::<T>(), mem::align_of::<MaybeUninit<T>>());
+    }
+
+    #[test]
+    fn maybe_uninit_layout() {
+        // Representative types, covering padding, ZSTs, niches and fat pointers.
+        assert_same_layout::<u8>();
+        assert_same_layout::<(u8, u64)>();
+        assert_same_layout::<[u16; 3]>();
+        assert_same_layout::<()>();
+        assert_same_layout::<core::marker::PhantomPinned>();
+        assert_same_layout::<Option<&u8>>();
+        assert_same_layout::<&str>();
+        assert_same_layout::<PinOption<u64>>();
+    }
 }
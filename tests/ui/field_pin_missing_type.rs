@@ -0,0 +1,12 @@
+use std::marker::PhantomPinned;
+
+use pin_macros::field_pin;
+
+struct Node {
+    inner: PhantomPinned,
+}
+impl Node {
+    field_pin!(inner);
+}
+
+fn main() {}
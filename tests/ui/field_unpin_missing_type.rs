@@ -0,0 +1,12 @@
+use std::marker::PhantomPinned;
+
+use pin_macros::field_unpin;
+
+struct Node {
+    inner: PhantomPinned,
+}
+impl Node {
+    field_unpin!(inner);
+}
+
+fn main() {}
@@ -0,0 +1,20 @@
+use std::{marker::PhantomPinned, mem::MaybeUninit, pin::Pin};
+
+use pin_macros::{pin_field_init, PinOption};
+
+struct Inner(PhantomPinned);
+impl Inner {
+    fn init(ptr: Pin<&mut MaybeUninit<Self>>) -> Pin<&mut Self> {
+        unsafe { Pin::new_unchecked(ptr.get_unchecked_mut().write(Self(PhantomPinned))) }
+    }
+}
+struct Outer {
+    inner: PinOption<Inner>,
+}
+impl Outer {
+    fn start(self: Pin<&mut Self>, inner: PinOption<Inner>) {
+        pin_field_init!(Inner: init(inner));
+    }
+}
+
+fn main() {}
@@ -0,0 +1,12 @@
+use pin_macros::pin_init;
+
+struct Counter {
+    count: u32,
+}
+impl<'a> Counter {
+    pin_init!(fn init<'a>(count: u32) {
+        this.count = count;
+    });
+}
+
+fn main() {}
@@ -0,0 +1,11 @@
+use std::marker::PhantomPinned;
+
+use pin_macros::pin_new;
+
+struct Node {
+    marker: PhantomPinned,
+}
+
+fn main() {
+    pin_new!(node: Node = init());
+}
@@ -0,0 +1,5 @@
+use pin_macros::pin_new;
+
+fn main() {
+    pin_new!(node = init());
+}